@@ -1,22 +1,44 @@
 use std::env;
 use std::net::TcpListener;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use task_reminder::comm::parse_quiet_hours;
 use task_reminder::daemon::serve;
-use task_reminder::scheduler::Scheduler;
-use task_reminder::task_manager::TaskManager;
+use task_reminder::scheduler::{Scheduler, SchedulerConfig};
+use task_reminder::task_manager::{MissedFire, TaskManager};
 
 fn main() -> Result<()> {
     task_reminder::setup_logger();
     let addr = env::var("FMN_DAEMON_ADDR").unwrap_or_else(|_| "127.0.0.1:8082".to_owned());
     let fmn_dir =
         env::var("FMN_DIR").unwrap_or_else(|_| format!("{}/.fmn", env::var("HOME").unwrap()));
-    spawn_daemon(addr, fmn_dir)
+    // what to do with fires missed during a suspend or downtime: late, latest or skip
+    let missed = match env::var("FMN_MISSED") {
+        Ok(missed) => MissedFire::from_str(&missed, true).map_err(|e| anyhow!(e))?,
+        Err(_) => MissedFire::default(),
+    };
+    // hold notifications back every day during these hours, e.g. 22:00-07:00
+    let quiet_hours = match env::var("FMN_QUIET_HOURS") {
+        Ok(quiet_hours) => Some(parse_quiet_hours(&quiet_hours)?),
+        Err(_) => None,
+    };
+    // seeds the jitter of every task alike rather than by its id
+    let jitter_seed = match env::var("FMN_JITTER_SEED") {
+        Ok(seed) => Some(seed.parse().context("FMN_JITTER_SEED should be a number")?),
+        Err(_) => None,
+    };
+    let config = SchedulerConfig {
+        missed,
+        quiet_hours,
+        jitter_seed,
+    };
+    spawn_daemon(addr, fmn_dir, config)
 }
 
-pub fn spawn_daemon(addr: String, fmn_dir: String) -> Result<()> {
+pub fn spawn_daemon(addr: String, fmn_dir: String, config: SchedulerConfig) -> Result<()> {
     std::fs::create_dir_all(&fmn_dir)?;
-    let scheduler = Scheduler::new();
+    let scheduler = Scheduler::with_config(config);
     let tm = TaskManager::new(&fmn_dir, scheduler)?;
     start_listen(&addr, tm)?;
     Ok(())
@@ -3,13 +3,26 @@
 use anyhow::{anyhow, Result};
 use clap::{Parser, Subcommand};
 use std::env;
-use task_reminder::format::tabular_output;
+use std::path::PathBuf;
+use std::time::Duration;
+use task_reminder::format::{tabular_output, upcoming_output};
 
+use task_reminder::calendar::load_calendar;
 use task_reminder::client::send_request;
 use task_reminder::comm::{
-    get_local_now, parse_at, parse_duration, ContextCommand, Request, Response,
+    get_local_now, in_timezone, parse_at_in, parse_between, parse_cron, parse_duration,
+    parse_month_and_day, parse_month_day, parse_timezone, parse_weekdays, ContextCommand,
+    DndCommand, Request, Response,
 };
-use task_reminder::task_manager::ClockType;
+use task_reminder::dispatch::DuringDnd;
+use task_reminder::escalation::Escalation;
+use task_reminder::natural::parse_natural;
+use task_reminder::pomodoro::Pomodoro;
+use task_reminder::rrule::parse_rrule;
+use task_reminder::task_manager::{ActiveHours, ClockType, MissedFire, TaskOptions};
+use task_reminder::timer::Timer;
+use time::OffsetDateTime;
+use time_tz::Tz;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about=None)]
@@ -18,6 +31,8 @@ struct Cli {
     command: Command,
 }
 
+// parsed once per run, so the size of the Add options doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Command {
     Add {
@@ -30,15 +45,136 @@ enum Command {
 
         #[arg(short, long)]
         sound_path: Option<String>,
+
+        // IANA time zone of the given wall-clock time, e.g. Europe/Berlin; local by default
+        #[arg(long, global = true)]
+        tz: Option<String>,
+
+        // what to do with fires missed during a suspend or downtime; the daemon's by default
+        #[arg(long, global = true, value_enum)]
+        missed: Option<MissedFire>,
+
+        // notify again with this interval after a fire until `fmn ack`
+        #[arg(long, global = true, num_args = 0..=1, default_missing_value = "5m")]
+        until_ack: Option<String>,
+
+        // notify a fire at low urgency, with a sound after 5m, then critically every minute
+        // until `fmn ack` or for this long
+        #[arg(long, global = true, num_args = 0..=1, default_missing_value = "30m")]
+        escalate: Option<String>,
+
+        // stop a recurring task after this date (the end of the day) or date and time
+        #[arg(long, global = true)]
+        until: Option<String>,
+
+        // stop a recurring task after this many fires
+        #[arg(long, global = true)]
+        count: Option<u32>,
+
+        // only fire a periodic task inside this window, e.g. 09:00-18:00
+        #[arg(long, global = true)]
+        between: Option<String>,
+
+        // only fire a periodic task from monday to friday
+        #[arg(long, global = true)]
+        weekdays: bool,
+
+        // what to do with a fire during do-not-disturb; deferred by default
+        #[arg(long, global = true, value_enum)]
+        during_dnd: Option<DuringDnd>,
+
+        // notify this long before a one-shot task too, e.g. 15m,5m
+        #[arg(long, global = true, value_delimiter = ',')]
+        warn: Vec<String>,
+
+        // fire a recurring task at a random offset up to this after each occurrence, e.g. 10m
+        #[arg(long, global = true)]
+        jitter: Option<String>,
+
+        // skip the dates of the events in this .ics file, e.g. ~/.fmn/holidays.ics
+        #[arg(long, global = true)]
+        skip_calendar: Option<String>,
     },
     Rm {
         task_id: String,
     },
+    // stop notifying a task added with --until-ack again
+    Ack {
+        task_id: String,
+    },
+    // keep a task without firing it until `fmn resume`
+    Pause {
+        task_id: String,
+    },
+    Resume {
+        task_id: String,
+    },
+    // fire a recently fired task once more after duration
+    Snooze {
+        task_id: String,
+        #[arg(default_value = "10m")]
+        duration: String,
+    },
     List,
+    // the tasks firing next in chronological order
+    Next {
+        #[arg(default_value_t = 5)]
+        limit: usize,
+        // only those firing within this duration, e.g. 1d
+        #[arg(long)]
+        within: Option<String>,
+    },
     Context {
         #[command(subcommand)]
         command: ContextCommand,
     },
+    // do-not-disturb: defer or drop notifications for a while
+    Dnd {
+        #[command(subcommand)]
+        command: DndCommand,
+    },
+    Pomodoro {
+        #[command(subcommand)]
+        command: PomodoroCommand,
+    },
+    // a countdown notified once at its end, e.g. fmn timer 25m tea
+    #[command(args_conflicts_with_subcommands = true)]
+    Timer {
+        #[command(subcommand)]
+        command: Option<TimerCommand>,
+        #[arg(required = true)]
+        duration: Option<String>,
+        #[arg(default_value = "timer")]
+        description: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PomodoroCommand {
+    // notify at every change between work and breaks; stop it with `fmn rm`
+    Start {
+        #[arg(default_value = "pomodoro")]
+        description: String,
+        #[arg(long, default_value = "25m")]
+        work: String,
+        #[arg(long = "break", default_value = "5m")]
+        short_break: String,
+        #[arg(long, default_value = "15m")]
+        long_break: String,
+        #[arg(long, default_value_t = 4)]
+        rounds: u32,
+    },
+    // the current phase and the time left of every running pomodoro
+    Status,
+}
+
+#[derive(Subcommand)]
+enum TimerCommand {
+    // the time left of every timer
+    Status,
+    // stop the countdown, keeping the time left
+    Pause { task_id: String },
+    Resume { task_id: String },
 }
 
 #[derive(Subcommand)]
@@ -46,30 +182,105 @@ enum AddCommand {
     After {
         duration: String,
     },
+    // 19:30, 11-03 14:00, 2026-11-03 14:00 or 2026-11-03T14:00:00+01:00
     At {
-        time: String,
+        #[arg(num_args = 1..=2, required = true)]
+        time: Vec<String>,
         #[arg(short, long)]
         per_day: bool,
+        // only fire on these weekdays, e.g. mon,wed,fri; implies --per-day
+        #[arg(short, long)]
+        on: Option<String>,
     },
     Per {
         duration: String,
+        // the first fire, e.g. 2026-11-03 09:00; a time alone like 09:00 only sets the phase, so
+        // that per 1h fires on the hour; a period after now by default
+        #[arg(long)]
+        start: Option<String>,
+    },
+    // tomorrow at 6pm, next monday 9am, in 2 hours, friday noon
+    When {
+        expression: String,
+    },
+    Cron {
+        expression: String,
+    },
+    // day: 15, last, 2nd-tue
+    Monthly {
+        day: String,
+        #[arg(default_value = "9:00")]
+        time: String,
+    },
+    // date: 03-14
+    Yearly {
+        date: String,
+        #[arg(default_value = "9:00")]
+        time: String,
+    },
+    // rule: FREQ=WEEKLY;BYDAY=MO,WE;COUNT=10
+    Rrule {
+        rule: String,
+        // the first occurrence and the time of day, now by default
+        #[arg(long)]
+        start: Option<String>,
     },
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let dest = env::var("FMN_DAEMON_ADDR").unwrap_or_else(|_| "127.0.0.1:8082".to_owned());
     let request = match cli.command {
         Command::Add {
             description,
             command,
             mut image_path,
             mut sound_path,
+            tz,
+            missed,
+            until_ack,
+            escalate,
+            until,
+            count,
+            between,
+            weekdays,
+            during_dnd,
+            warn,
+            jitter,
+            skip_calendar,
         } => {
+            // every moment of the task is parsed relative to the same now
+            let now = get_local_now();
+            if let Some(interval) = &until_ack {
+                let interval = parse_duration(interval)?;
+                if interval.as_secs() == 0 {
+                    return Err(anyhow!("--until-ack <interval> should not be 0"));
+                }
+                time::Duration::try_from(interval)
+                    .ok()
+                    .and_then(|interval| now.checked_add(interval))
+                    .ok_or_else(|| anyhow!("--until-ack <interval> is too long"))?;
+            }
+            let escalation = match escalate {
+                Some(_) if until_ack.is_some() => {
+                    return Err(anyhow!("--escalate already notifies again until `fmn ack`"));
+                }
+                Some(end) if parse_duration(&end)?.as_secs() == 0 => {
+                    return Err(anyhow!("--escalate <duration> should not be 0"));
+                }
+                Some(end) => Some(Escalation::profile(end)),
+                None => None,
+            };
+            let timezone = tz.as_deref().map(parse_timezone).transpose()?;
+            let mut start = None; // of a periodic task
             let clock_type = match command {
-                AddCommand::At { time, per_day } => {
-                    let next_fire = parse_at(&time)?;
-                    if per_day {
-                        ClockType::OncePerDay(next_fire.hour(), next_fire.minute())
+                AddCommand::At { time, per_day, on } => {
+                    let next_fire = parse_at_in(&time.join(" "), now, timezone)?;
+                    if let Some(weekdays) = on {
+                        let weekdays = parse_weekdays(&weekdays)?;
+                        ClockType::OncePerDay(next_fire.hour(), next_fire.minute(), weekdays)
+                    } else if per_day {
+                        ClockType::OncePerDay(next_fire.hour(), next_fire.minute(), vec![])
                     } else {
                         ClockType::Once(next_fire)
                     }
@@ -79,14 +290,146 @@ fn main() -> Result<()> {
                     if duration.as_secs() == 0 {
                         return Err(anyhow!("after <duration> should not be 0"));
                     }
-                    let next_fire = get_local_now() + duration;
-                    ClockType::Once(next_fire)
+                    ClockType::Once(now + duration)
                 }
-                AddCommand::Per { duration } => {
-                    let _ = parse_duration(&duration)?;
+                AddCommand::Per {
+                    duration,
+                    start: first_fire,
+                } => {
+                    if first_fire.is_some() && (between.is_some() || weekdays) {
+                        // the window sets the phase of the fires every day
+                        return Err(anyhow!(
+                            "--start doesn't apply with --between or --weekdays"
+                        ));
+                    }
+                    let period = parse_duration(&duration)?.max(Duration::from_secs(1));
+                    start = Some(match first_fire {
+                        Some(first_fire) => period_start(&first_fire, period, now, timezone)?,
+                        None => now + period,
+                    });
                     ClockType::Period(duration)
                 }
+                AddCommand::When { expression } => {
+                    ClockType::Once(parse_natural(&expression, now, timezone)?)
+                }
+                AddCommand::Cron { expression } => {
+                    let _ = parse_cron(&expression)?;
+                    ClockType::Cron(expression)
+                }
+                AddCommand::Monthly { day, time } => {
+                    let month_day = parse_month_day(&day)?;
+                    let next_fire = parse_at_in(&time, now, timezone)?;
+                    ClockType::Monthly(month_day, next_fire.hour(), next_fire.minute())
+                }
+                AddCommand::Yearly { date, time } => {
+                    let (month, day) = parse_month_and_day(&date)?;
+                    let next_fire = parse_at_in(&time, now, timezone)?;
+                    ClockType::Yearly(month, day, next_fire.hour(), next_fire.minute())
+                }
+                AddCommand::Rrule { rule, start } => {
+                    let _ = parse_rrule(&rule)?;
+                    let dtstart = match start {
+                        Some(start) => parse_at_in(&start, now, timezone)?,
+                        None => in_timezone(now, timezone)
+                            .replace_second(0)?
+                            .replace_nanosecond(0)?,
+                    };
+                    ClockType::RRule(rule, dtstart)
+                }
+            };
+            if matches!(clock_type, ClockType::Once(_)) && (until.is_some() || count.is_some()) {
+                return Err(anyhow!("--until and --count only apply to recurring tasks"));
+            }
+            let active_hours = if between.is_some() || weekdays {
+                let period = match &clock_type {
+                    ClockType::Period(period) => parse_duration(period)?,
+                    _ => {
+                        return Err(anyhow!(
+                            "--between and --weekdays only apply to periodic tasks"
+                        ))
+                    }
+                };
+                let (start, end) = match between {
+                    Some(between) => parse_between(&between)?,
+                    None => ((0, 0), (23, 59)),
+                };
+                let minutes = |(hour, minute): (u8, u8)| hour as u64 * 60 + minute as u64;
+                if period.as_secs() > (minutes(end) - minutes(start)) * 60 {
+                    return Err(anyhow!("the period should fit in the active hours"));
+                }
+                let weekdays = if weekdays {
+                    parse_weekdays("mon,tue,wed,thu,fri")?
+                } else {
+                    vec![]
+                };
+                Some(ActiveHours {
+                    start,
+                    end,
+                    weekdays,
+                })
+            } else {
+                None
             };
+            if !warn.is_empty() {
+                let fire = match clock_type {
+                    ClockType::Once(fire) => fire,
+                    _ => return Err(anyhow!("--warn only applies to one-shot tasks")),
+                };
+                for lead in &warn {
+                    let lead = parse_duration(lead)?;
+                    if lead.as_secs() == 0 {
+                        return Err(anyhow!("--warn <lead time> should not be 0"));
+                    }
+                    // also keeps the pre-alert within the dates the daemon can handle
+                    let lead = time::Duration::try_from(lead)?;
+                    if lead >= fire - now {
+                        return Err(anyhow!("--warn <lead time> should end before the fire"));
+                    }
+                }
+            }
+            if let Some(jitter) = &jitter {
+                let jitter = parse_duration(jitter)?;
+                match &clock_type {
+                    ClockType::Once(_) => {
+                        return Err(anyhow!("--jitter only applies to recurring tasks"));
+                    }
+                    ClockType::Period(period) if jitter > parse_duration(period)? => {
+                        return Err(anyhow!("--jitter should not be longer than the period"));
+                    }
+                    _ if jitter.as_secs() == 0 || jitter.as_secs() > 24 * 3600 => {
+                        return Err(anyhow!("--jitter should be between 1s and 1d"));
+                    }
+                    _ => {}
+                }
+            }
+            let skip_calendar = match skip_calendar {
+                Some(_) if matches!(clock_type, ClockType::Once(_)) => {
+                    return Err(anyhow!("--skip-calendar only applies to recurring tasks"));
+                }
+                // the daemon may run in another directory
+                Some(calendar) => {
+                    let calendar = match calendar.strip_prefix("~/") {
+                        Some(relative) => PathBuf::from(env::var("HOME")?).join(relative),
+                        None => env::current_dir()?.join(calendar),
+                    };
+                    load_calendar(&calendar)?;
+                    Some(calendar.to_string_lossy().into_owned())
+                }
+                None => None,
+            };
+            if count == Some(0) {
+                return Err(anyhow!("--count should not be 0"));
+            }
+            let until = until
+                .map(|until| {
+                    // a date alone means until the end of that day
+                    if until.contains(':') {
+                        parse_at_in(&until, now, timezone)
+                    } else {
+                        parse_at_in(&format!("{until} 23:59"), now, timezone)
+                    }
+                })
+                .transpose()?;
             if image_path.is_none() {
                 if let Ok(system_image_path) = env::var("FMN_IMAGE_PATH") {
                     image_path = Some(system_image_path);
@@ -97,23 +440,144 @@ fn main() -> Result<()> {
                     sound_path = Some(system_sound_path);
                 }
             }
-            Request::Add(description, clock_type, image_path, sound_path)
+            let options = TaskOptions {
+                timezone: tz,
+                missed,
+                until_ack,
+                until,
+                count,
+                active_hours,
+                during_dnd: during_dnd.unwrap_or_default(),
+                warn,
+                jitter,
+                skip_calendar,
+                start,
+                escalation,
+            };
+            Request::Add(
+                description,
+                clock_type,
+                image_path,
+                sound_path,
+                Box::new(options),
+            )
         }
         Command::Rm { task_id } => Request::Cancel(task_id),
-        Command::List => Request::Show,
+        Command::Ack { task_id } => Request::Ack(task_id),
+        Command::Pause { task_id } => Request::Pause(task_id),
+        Command::Resume { task_id } => Request::Resume(task_id),
+        Command::Snooze { task_id, duration } => {
+            if parse_duration(&duration)?.as_secs() == 0 {
+                return Err(anyhow!("snooze <duration> should not be 0"));
+            }
+            Request::Snooze(task_id, duration)
+        }
+        Command::List => return list_tasks(&dest),
+        Command::Next { limit, within } => {
+            if let Some(within) = &within {
+                parse_duration(within)?;
+            }
+            Request::Upcoming {
+                limit,
+                window: within,
+            }
+        }
         Command::Context { command } => Request::ContextRequest(command),
+        Command::Dnd { command } => {
+            if let DndCommand::On {
+                duration: Some(duration),
+            } = &command
+            {
+                if parse_duration(duration)?.as_secs() == 0 {
+                    return Err(anyhow!("dnd on <duration> should not be 0"));
+                }
+            }
+            Request::Dnd(command)
+        }
+        Command::Pomodoro {
+            command:
+                PomodoroCommand::Start {
+                    description,
+                    work,
+                    short_break,
+                    long_break,
+                    rounds,
+                },
+        } => {
+            for duration in [&work, &short_break, &long_break] {
+                let duration = parse_duration(duration)?;
+                if duration.as_secs() == 0 {
+                    return Err(anyhow!("the durations of a pomodoro should not be 0"));
+                }
+                time::Duration::try_from(duration)?;
+            }
+            if rounds == 0 {
+                return Err(anyhow!("--rounds should not be 0"));
+            }
+            let pomodoro = Pomodoro {
+                start: get_local_now(),
+                work,
+                short_break,
+                long_break,
+                rounds,
+            };
+            if !pomodoro.is_representable() {
+                return Err(anyhow!("the pomodoro should not end that far away"));
+            }
+            Request::Add(
+                description,
+                ClockType::Pomodoro(pomodoro),
+                env::var("FMN_IMAGE_PATH").ok(),
+                env::var("FMN_SOUND_PATH").ok(),
+                Box::default(),
+            )
+        }
+        Command::Pomodoro {
+            command: PomodoroCommand::Status,
+        } => return pomodoro_status(&dest),
+        Command::Timer {
+            command: Some(command),
+            ..
+        } => match command {
+            TimerCommand::Status => return timer_status(&dest),
+            TimerCommand::Pause { task_id } => Request::Pause(task_id),
+            TimerCommand::Resume { task_id } => Request::Resume(task_id),
+        },
+        Command::Timer {
+            command: None,
+            duration,
+            description,
+        } => {
+            let duration = duration.expect("the duration is required without a subcommand");
+            let end = parse_duration(&duration)?;
+            if end.as_secs() == 0 {
+                return Err(anyhow!("timer <duration> should not be 0"));
+            }
+            Request::Add(
+                description,
+                ClockType::Timer(Timer::new(duration, get_local_now() + end)),
+                env::var("FMN_IMAGE_PATH").ok(),
+                env::var("FMN_SOUND_PATH").ok(),
+                Box::default(),
+            )
+        }
     };
 
     //println!("request is {:?}", request);
-    let dest = env::var("FMN_DAEMON_ADDR").unwrap_or_else(|_| "127.0.0.1:8082".to_owned());
     match send_request(request.clone(), &dest) {
         Ok(response) => match response {
-            Response::GetTasks(tasks) => {
-                println!("{}", tabular_output(&tasks));
+            Response::Upcoming(upcoming) if upcoming.is_empty() => {
+                println!("no upcoming task");
+            }
+            Response::Upcoming(upcoming) => {
+                println!("{}", upcoming_output(&upcoming));
             }
             Response::GetContexts(contexts) => {
                 println!(" * {}", contexts.join("\n   "));
             }
+            Response::DndStatus(dnd) => {
+                println!("do-not-disturb: {dnd}");
+            }
             _ => println!("success: {:?}", response),
         },
         Err(e) => {
@@ -122,3 +586,90 @@ fn main() -> Result<()> {
     }
     Ok(())
 }
+
+// the tasks with their next fires
+fn list_tasks(dest: &str) -> Result<()> {
+    let tasks = match send_request(Request::Show, dest)? {
+        Response::GetTasks(tasks) => tasks,
+        response => return Err(anyhow!("unexpected response: {:?}", response)),
+    };
+    let upcoming = Request::Upcoming {
+        limit: usize::MAX,
+        window: None,
+    };
+    let next_fires = match send_request(upcoming, dest)? {
+        Response::Upcoming(upcoming) => upcoming
+            .into_iter()
+            .map(|(at, task)| (task.task_id, at))
+            .collect(),
+        response => return Err(anyhow!("unexpected response: {:?}", response)),
+    };
+    println!("{}", tabular_output(&tasks, &next_fires));
+    Ok(())
+}
+
+// the first fire given by --start; a time of the day alone is moved back by whole periods up to
+// now, so that the fires keep its phase from now on
+fn period_start(
+    first_fire: &str,
+    period: Duration,
+    now: OffsetDateTime,
+    timezone: Option<&Tz>,
+) -> Result<OffsetDateTime> {
+    let start = parse_at_in(first_fire, now, timezone)?;
+    // a date is followed by a space or a T
+    if first_fire.trim().contains([' ', 'T']) {
+        return Ok(start);
+    }
+    let period = time::Duration::try_from(period)?;
+    let periods = (start - now).whole_nanoseconds() / period.whole_nanoseconds();
+    Ok(start - period * periods as i32)
+}
+
+fn timer_status(dest: &str) -> Result<()> {
+    let tasks = match send_request(Request::Show, dest)? {
+        Response::GetTasks(tasks) => tasks,
+        response => return Err(anyhow!("unexpected response: {:?}", response)),
+    };
+    let now = get_local_now();
+    let mut running = false;
+    for task in tasks {
+        if let ClockType::Timer(timer) = &task.clock_type {
+            println!(
+                "{} {}: {}",
+                task.task_id,
+                task.description,
+                timer.status(now)
+            );
+            running = true;
+        }
+    }
+    if !running {
+        println!("no timer running");
+    }
+    Ok(())
+}
+
+fn pomodoro_status(dest: &str) -> Result<()> {
+    let tasks = match send_request(Request::Show, dest)? {
+        Response::GetTasks(tasks) => tasks,
+        response => return Err(anyhow!("unexpected response: {:?}", response)),
+    };
+    let now = get_local_now();
+    let mut running = false;
+    for task in tasks {
+        if let ClockType::Pomodoro(pomodoro) = &task.clock_type {
+            println!(
+                "{} {}: {}",
+                task.task_id,
+                task.description,
+                pomodoro.status(now)
+            );
+            running = true;
+        }
+    }
+    if !running {
+        println!("no pomodoro running");
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+// exclusion calendars: the dates of the events in local .ics files, e.g. public holidays, on
+// which recurring tasks with --skip-calendar don't fire
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use time::{Date, Month, PrimitiveDateTime, Time};
+
+use crate::comm::in_timezone;
+
+const MAX_EVENT_DAYS: usize = 366; // a bound on the days excluded by a single event
+
+// the calendars the daemon has loaded so far, reloaded when their files change
+static CALENDARS: Lazy<Mutex<HashMap<PathBuf, ExclusionCalendar>>> = Lazy::new(Default::default);
+
+struct ExclusionCalendar {
+    modified: Option<SystemTime>,
+    dates: HashSet<Date>,
+}
+
+impl ExclusionCalendar {
+    fn load(path: &Path) -> Self {
+        let modified = modified_time(path);
+        let dates = load_calendar(path).unwrap_or_else(|e| {
+            error!("fail to load exclusion calendar {:?}: {}", path, e);
+            HashSet::new()
+        });
+        info!("load {} excluded dates from {:?}", dates.len(), path);
+        ExclusionCalendar { modified, dates }
+    }
+}
+
+pub fn load_calendar(path: &Path) -> Result<HashSet<Date>> {
+    let content = fs::read_to_string(path).context(format!("fail to read {path:?}"))?;
+    parse_ics(&content).context(format!("invalid calendar {path:?}"))
+}
+
+// whether a date is in the calendar at path, which is loaded on the first use
+pub fn is_excluded(path: &Path, date: Date) -> bool {
+    CALENDARS
+        .lock()
+        .unwrap()
+        .entry(path.to_owned())
+        .or_insert_with(|| ExclusionCalendar::load(path))
+        .dates
+        .contains(&date)
+}
+
+// reloads the calendars whose files have changed since they were loaded and returns their paths
+pub fn reload_changed() -> Vec<PathBuf> {
+    let mut calendars = CALENDARS.lock().unwrap();
+    let mut changed = vec![];
+    for (path, calendar) in calendars.iter_mut() {
+        if modified_time(path) != calendar.modified {
+            *calendar = ExclusionCalendar::load(path);
+            changed.push(path.clone());
+        }
+    }
+    changed
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// the dates covered by the VEVENTs of an iCalendar(RFC 5545) file; an all-day event covers the
+// dates from its DTSTART until the day before its DTEND, any other one only its start date
+pub fn parse_ics(content: &str) -> Result<HashSet<Date>> {
+    let mut dates = HashSet::new();
+    let mut event: Option<(Option<IcsDate>, Option<IcsDate>)> = None;
+    for line in unfold(content) {
+        let (name, value) = match line.split_once(':') {
+            Some((name, value)) => (name, value.trim()),
+            None => continue,
+        };
+        // parameters such as VALUE=DATE or TZID=... follow the property name
+        let property = name.split(';').next().unwrap_or_default().to_uppercase();
+        match (property.as_str(), &mut event) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => event = Some((None, None)),
+            ("END", Some((start, end))) if value.eq_ignore_ascii_case("VEVENT") => {
+                let start = start.ok_or_else(|| anyhow!("an event without DTSTART"))?;
+                dates.extend(event_dates(start, *end));
+                event = None;
+            }
+            ("DTSTART", Some((start, _))) => *start = Some(parse_ics_date(value)?),
+            ("DTEND", Some((_, end))) => *end = Some(parse_ics_date(value)?),
+            _ => {}
+        }
+    }
+    Ok(dates)
+}
+
+#[derive(Clone, Copy)]
+enum IcsDate {
+    Date(Date),     // an all-day value
+    DateTime(Date), // the date of a date-time value
+}
+
+fn event_dates(start: IcsDate, end: Option<IcsDate>) -> Vec<Date> {
+    match (start, end) {
+        (IcsDate::Date(start), Some(IcsDate::Date(end))) if end > start => {
+            std::iter::successors(Some(start), |date| date.next_day())
+                .take_while(|date| *date < end)
+                .take(MAX_EVENT_DAYS)
+                .collect()
+        }
+        (IcsDate::Date(date) | IcsDate::DateTime(date), _) => vec![date],
+    }
+}
+
+// 20261225, 20261225T090000 or 20261225T080000Z; a UTC time is taken in the local time zone
+fn parse_ics_date(value: &str) -> Result<IcsDate> {
+    let re = Regex::new(r"^(\d{4})(\d{2})(\d{2})(?:T(\d{2})(\d{2})(\d{2})(Z)?)?$").unwrap();
+    let invalid = || anyhow!("invalid date {value}");
+    let captures = re.captures(value).ok_or_else(invalid)?;
+    let number = |index: usize| captures[index].parse::<u8>().map_err(|_| invalid());
+    let month = Month::try_from(number(2)?).map_err(|_| invalid())?;
+    let date =
+        Date::from_calendar_date(captures[1].parse()?, month, number(3)?).map_err(|_| invalid())?;
+    if captures.get(4).is_none() {
+        return Ok(IcsDate::Date(date));
+    }
+    let time = Time::from_hms(number(4)?, number(5)?, number(6)?).map_err(|_| invalid())?;
+    let moment = PrimitiveDateTime::new(date, time);
+    if captures.get(7).is_some() {
+        Ok(IcsDate::DateTime(
+            in_timezone(moment.assume_utc(), None).date(),
+        ))
+    } else {
+        Ok(IcsDate::DateTime(date))
+    }
+}
+
+// joins the content lines folded by a leading space or tab
+fn unfold(content: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+    lines
+}
@@ -0,0 +1,172 @@
+// where the scheduler gets the time from and how it sleeps, so that tests can drive it through
+// days, DST changes and suspends without waiting
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use time::OffsetDateTime;
+use tokio::sync::watch;
+
+const SETTLE_TIMEOUT_SECS: u64 = 5; // a fake clock waits this long for the scheduler to catch up
+
+pub type Sleep = Pin<Box<dyn Future<Output = ()>>>;
+
+pub trait Clock: Send + Sync {
+    // the wall clock, which jumps on a suspend or a clock change
+    fn now_utc(&self) -> OffsetDateTime;
+    // the monotonic clock, which stops while the host sleeps
+    fn instant(&self) -> Instant;
+    // a monotonic sleep
+    fn sleep(&self, duration: Duration) -> Sleep;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+// a clock which only moves when told to; its methods moving the time return once the sleepers
+// they wake have gone to sleep again, i.e. once the scheduler has handled the new time
+#[derive(Clone)]
+pub struct FakeClock {
+    state: Arc<(Mutex<FakeState>, Condvar)>,
+    elapsed: watch::Sender<Duration>,
+    elapsed_receiver: watch::Receiver<Duration>,
+}
+
+struct FakeState {
+    wall: OffsetDateTime,
+    base: Instant,
+    elapsed: Duration,               // on the monotonic clock
+    sleeps: u64,                     // how many sleeps have been started
+    deadlines: Vec<(u64, Duration)>, // of the pending sleeps, by their number
+}
+
+impl FakeClock {
+    pub fn new(now: OffsetDateTime) -> Self {
+        let (elapsed, elapsed_receiver) = watch::channel(Duration::ZERO);
+        let state = FakeState {
+            wall: now,
+            base: Instant::now(),
+            elapsed: Duration::ZERO,
+            sleeps: 0,
+            deadlines: vec![],
+        };
+        FakeClock {
+            state: Arc::new((Mutex::new(state), Condvar::new())),
+            elapsed,
+            elapsed_receiver,
+        }
+    }
+
+    // moves both the wall and the monotonic clock
+    pub fn advance(&self, duration: Duration) {
+        self.move_clocks(duration, duration);
+    }
+
+    pub fn advance_to(&self, moment: OffsetDateTime) {
+        let duration = (moment - self.now_utc())
+            .try_into()
+            .expect("a fake clock only moves forward");
+        self.advance(duration);
+    }
+
+    // moves the wall clock only, as the host sleeping for this long does
+    pub fn suspend(&self, duration: Duration) {
+        self.move_clocks(duration, Duration::ZERO);
+    }
+
+    // how many sleeps have been started, e.g. to wait with settle for the scheduler to handle a
+    // command sent after that
+    pub fn sleeps(&self) -> u64 {
+        self.state.0.lock().unwrap().sleeps
+    }
+
+    // waits until a sleep after the given number has been started
+    pub fn settle(&self, sleeps: u64) {
+        let (state, started) = &*self.state;
+        let state = state.lock().unwrap();
+        let (_state, timeout) = started
+            .wait_timeout_while(state, Duration::from_secs(SETTLE_TIMEOUT_SECS), |state| {
+                state.sleeps <= sleeps
+            })
+            .unwrap();
+        assert!(
+            !timeout.timed_out(),
+            "the scheduler didn't go to sleep again"
+        );
+    }
+
+    fn move_clocks(&self, wall: Duration, monotonic: Duration) {
+        let (sleeps, wakes) = {
+            let mut state = self.state.0.lock().unwrap();
+            state.wall += wall;
+            state.elapsed += monotonic;
+            let elapsed = state.elapsed;
+            self.elapsed.send_replace(elapsed);
+            let wakes = state.deadlines.iter().any(|(_, at)| *at <= elapsed);
+            (state.sleeps, wakes)
+        };
+        if wakes {
+            self.settle(sleeps);
+        }
+    }
+}
+
+impl Clock for FakeClock {
+    fn now_utc(&self) -> OffsetDateTime {
+        self.state.0.lock().unwrap().wall
+    }
+
+    fn instant(&self) -> Instant {
+        let state = self.state.0.lock().unwrap();
+        state.base + state.elapsed
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let state = self.state.clone();
+        let mut elapsed = self.elapsed_receiver.clone();
+        Box::pin(async move {
+            let (number, deadline) = {
+                let (state, started) = &*state;
+                let mut state = state.lock().unwrap();
+                state.sleeps += 1;
+                let sleep = (state.sleeps, state.elapsed + duration);
+                state.deadlines.push(sleep);
+                started.notify_all();
+                sleep
+            };
+            // the deadline is dropped with the sleep, even when it's cancelled
+            let _pending = PendingSleep { state, number };
+            while *elapsed.borrow_and_update() < deadline {
+                if elapsed.changed().await.is_err() {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+struct PendingSleep {
+    state: Arc<(Mutex<FakeState>, Condvar)>,
+    number: u64,
+}
+
+impl Drop for PendingSleep {
+    fn drop(&mut self) {
+        let mut state = self.state.0.lock().unwrap();
+        state.deadlines.retain(|(number, _)| *number != self.number);
+    }
+}
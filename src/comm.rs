@@ -1,3 +1,4 @@
+use std::iter;
 use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
@@ -6,19 +7,44 @@ use log::warn;
 use once_cell::sync::OnceCell;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use time::{OffsetDateTime, UtcOffset};
+use time::{
+    Date, Duration as TimeDuration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset,
+    Weekday,
+};
+use time_tz::{system, timezones, OffsetDateTimeExt, PrimitiveDateTimeExt, Tz};
 
-use crate::task_manager::{ClockType, Task, TaskContext, TaskID};
+use crate::clock::{Clock, SystemClock};
+use crate::dispatch::{Dnd, QuietHours};
+use crate::task_manager::{
+    ActiveHours, ClockType, MonthDay, Task, TaskContext, TaskID, TaskOptions,
+};
 
 static TZDIFF: OnceCell<UtcOffset> = OnceCell::new();
+static LOCAL_TIMEZONE: OnceCell<Option<&'static Tz>> = OnceCell::new();
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Request {
-    // name, clock_type, image_path, sound_path
-    Add(String, ClockType, Option<String>, Option<String>),
+    // name, clock_type, image_path, sound_path, options
+    Add(
+        String,
+        ClockType,
+        Option<String>,
+        Option<String>,
+        Box<TaskOptions>,
+    ),
     Cancel(TaskID),
+    Snooze(TaskID, String), // a recently fired task, duration
+    Ack(TaskID),            // stops notifying a task added with --until-ack again
+    Pause(TaskID),
+    Resume(TaskID),
     Show,
+    // the next fires of at most limit tasks, only those within window (a duration) from now
+    Upcoming {
+        limit: usize,
+        window: Option<String>,
+    },
     ContextRequest(ContextCommand),
+    Dnd(DndCommand),
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize, Clone)]
@@ -29,14 +55,27 @@ pub enum ContextCommand {
     Define { context: TaskContext },
 }
 
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone)]
+pub enum DndCommand {
+    // hold notifications back, until `fmn dnd off` or for duration
+    On { duration: Option<String> },
+    Off,
+    Status,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
     AddSuccess,    // for add task / define context
     RemoveSuccess, // for rm task/context
     Fail(String),
     GetTasks(Vec<Task>),
-    GetContexts(Vec<TaskContext>), // for list context
-    SetContextSuccess,             // for set context
+    Upcoming(Vec<(OffsetDateTime, Task)>), // in chronological order
+    GetContexts(Vec<TaskContext>),         // for list context
+    SetContextSuccess,                     // for set context
+    AckSuccess,
+    PauseSuccess,
+    ResumeSuccess,
+    DndStatus(Dnd), // the do-not-disturb in effect
 }
 
 pub fn parse_duration(duration: &str) -> Result<Duration> {
@@ -71,6 +110,343 @@ pub fn parse_duration(duration: &str) -> Result<Duration> {
     }
 }
 
+pub(crate) const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+pub(crate) const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+// 4 years so that "29 2" could still be found
+const CRON_SEARCH_DAYS: i64 = 366 * 4 + 1;
+// the 29th of february may be 8 years away, e.g. 2096 -> 2104
+const SEARCH_YEARS: i32 = 9;
+
+// a parsed 5-field crontab expression: minute hour day-of-month month day-of-week
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,       // bit i for minute i (0-59)
+    hours: u32,         // bit i for hour i (0-23)
+    days_of_month: u32, // bit i for day i (1-31)
+    months: u16,        // bit i for month i (1-12)
+    days_of_week: u8,   // bit i for weekday i (0-6, sunday is 0)
+    dom_restricted: bool,
+    dow_restricted: bool,
+}
+
+impl CronSchedule {
+    // returns the first matching minute strictly after `now`, in the offset of `now`
+    pub fn next_after(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        let mut candidate =
+            now.replace_second(0).ok()?.replace_nanosecond(0).ok()? + TimeDuration::minutes(1);
+        let deadline = now + TimeDuration::days(CRON_SEARCH_DAYS);
+        while candidate <= deadline {
+            if !self.day_matches(candidate) {
+                candidate = candidate.replace_time(Time::MIDNIGHT) + TimeDuration::days(1);
+                continue;
+            }
+            if self.hours & (1 << candidate.hour()) == 0 {
+                candidate = candidate.replace_minute(0).ok()? + TimeDuration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << candidate.minute()) == 0 {
+                candidate += TimeDuration::minutes(1);
+                continue;
+            }
+            return Some(candidate);
+        }
+        None
+    }
+
+    fn day_matches(&self, moment: OffsetDateTime) -> bool {
+        if self.months & (1 << moment.month() as u8) == 0 {
+            return false;
+        }
+        let dom = self.days_of_month & (1 << moment.day()) != 0;
+        let dow = self.days_of_week & (1 << moment.weekday().number_days_from_sunday()) != 0;
+        // same as vixie cron: if both fields are restricted, either one could match
+        match (self.dom_restricted, self.dow_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+pub fn parse_cron(expression: &str) -> Result<CronSchedule> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    if fields.len() != 5 {
+        return Err(anyhow!(
+            "invalid cron expression; expect 5 fields (minute hour day month weekday), e.g. \"30 9 * * 1-5\""
+        ));
+    }
+    let minutes = parse_cron_field(fields[0], 0, 59, &[]).context("invalid minute field")?;
+    let hours = parse_cron_field(fields[1], 0, 23, &[]).context("invalid hour field")?;
+    let days_of_month =
+        parse_cron_field(fields[2], 1, 31, &[]).context("invalid day-of-month field")?;
+    let months = parse_cron_field(fields[3], 1, 12, &MONTH_NAMES).context("invalid month field")?;
+    // 7 is also sunday
+    let mut days_of_week =
+        parse_cron_field(fields[4], 0, 7, &WEEKDAY_NAMES).context("invalid weekday field")?;
+    if days_of_week & (1 << 7) != 0 {
+        days_of_week = (days_of_week | 1) & !(1 << 7);
+    }
+    let schedule = CronSchedule {
+        minutes,
+        hours: hours as u32,
+        days_of_month: days_of_month as u32,
+        months: months as u16,
+        days_of_week: days_of_week as u8,
+        dom_restricted: !fields[2].starts_with('*'),
+        dow_restricted: !fields[4].starts_with('*'),
+    };
+    // whether it ever fires doesn't depend on when it's asked
+    if schedule.next_after(OffsetDateTime::UNIX_EPOCH).is_none() {
+        return Err(anyhow!("cron expression {expression} would never fire"));
+    }
+    Ok(schedule)
+}
+
+// parses one cron field like "*", "*/15", "1-5", "mon-fri" or "0,30" into a bitmask
+fn parse_cron_field(field: &str, min: u8, max: u8, names: &[&str]) -> Result<u64> {
+    let mut mask = 0_u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u8 = step.parse().context(format!("invalid step {step}"))?;
+                if step == 0 {
+                    return Err(anyhow!("step should not be 0"));
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_cron_value(start, min, names)?,
+                parse_cron_value(end, min, names)?,
+            )
+        } else {
+            let start = parse_cron_value(range, min, names)?;
+            // "5/10" means from 5 to the max with step 10
+            (start, if part.contains('/') { max } else { start })
+        };
+        if start < min || end > max || start > end {
+            return Err(anyhow!("{part} is out of range {min}-{max}"));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_cron_value(value: &str, min: u8, names: &[&str]) -> Result<u8> {
+    let lowercase = value.to_lowercase();
+    if let Some(index) = names.iter().position(|name| *name == lowercase) {
+        return Ok(index as u8 + min);
+    }
+    value.parse().context(format!("invalid value {value}"))
+}
+
+// parses a comma separated weekday list like "mon,wed,fri", of abbreviations or full names
+pub fn parse_weekdays(weekdays: &str) -> Result<Vec<Weekday>> {
+    let mut parsed = vec![];
+    for name in weekdays.split(',') {
+        let lowercase = name.trim().to_lowercase();
+        let weekday = iter::successors(Some(Weekday::Sunday), |weekday| Some(weekday.next()))
+            .zip(WEEKDAY_NAMES)
+            .find(|(weekday, abbreviation)| {
+                lowercase == *abbreviation || lowercase == weekday.to_string().to_lowercase()
+            })
+            .map(|(weekday, _)| weekday)
+            .ok_or_else(|| anyhow!("invalid weekday {name}; valid examples: mon, tue, sun"))?;
+        if !parsed.contains(&weekday) {
+            parsed.push(weekday);
+        }
+    }
+    parsed.sort_by_key(|weekday| weekday.number_days_from_monday());
+    Ok(parsed)
+}
+
+// parses "15", "last" or "2nd-tue" as a day in the month
+pub fn parse_month_day(month_day: &str) -> Result<MonthDay> {
+    let lowercase = month_day.to_lowercase();
+    if lowercase == "last" {
+        return Ok(MonthDay::LastDay);
+    }
+    if let Ok(day) = lowercase.parse::<u8>() {
+        if !(1..=31).contains(&day) {
+            return Err(anyhow!("day {day} should be within 1-31"));
+        }
+        return Ok(MonthDay::Day(day));
+    }
+    let re = Regex::new(r"^(?P<nth>[1-5])(?:st|nd|rd|th)?-(?P<weekday>[a-z]+)$").unwrap();
+    let captures = re
+        .captures(&lowercase)
+        .ok_or_else(|| anyhow!("invalid day in month; valid examples: 15, last, 2nd-tue, 1-mon"))?;
+    let nth = captures["nth"].parse().context("invalid nth weekday")?;
+    let weekday = parse_weekdays(&captures["weekday"])?[0];
+    Ok(MonthDay::NthWeekday(nth, weekday))
+}
+
+// parses "03-14" as (month, day)
+pub fn parse_month_and_day(month_and_day: &str) -> Result<(u8, u8)> {
+    let re = Regex::new(r"^(?P<month>\d{1,2})-(?P<day>\d{1,2})$").unwrap();
+    let captures = re
+        .captures(month_and_day)
+        .ok_or_else(|| anyhow!("invalid date format; valid examples: 03-14, 12-25"))?;
+    let month: u8 = captures["month"].parse().context("invalid month")?;
+    let day: u8 = captures["day"].parse().context("invalid day")?;
+    // 2000 is a leap year so that 02-29 is accepted
+    Date::from_calendar_date(2000, Month::try_from(month)?, day)
+        .context(format!("invalid date {month_and_day}"))?;
+    Ok((month, day))
+}
+
+// returns the first daily occurrence strictly after `now` on the given weekdays (any day if empty),
+// in the offset of `now`
+pub fn next_daily(
+    hour: u8,
+    minute: u8,
+    weekdays: &[Weekday],
+    now: OffsetDateTime,
+) -> Option<OffsetDateTime> {
+    let time = Time::from_hms(hour, minute, 0).ok()?;
+    let mut date = now.date();
+    for _ in 0..8 {
+        let candidate = now.replace_date(date).replace_time(time);
+        if candidate > now && (weekdays.is_empty() || weekdays.contains(&date.weekday())) {
+            return Some(candidate);
+        }
+        date = date.next_day()?;
+    }
+    None
+}
+
+// "09:00-18:00" into the start and the end of the window
+pub fn parse_between(between: &str) -> Result<((u8, u8), (u8, u8))> {
+    let (start, end) = parse_time_window(between)?;
+    if start >= end {
+        return Err(anyhow!(
+            "the start of {between} should be before its end within a day"
+        ));
+    }
+    Ok((start, end))
+}
+
+// "22:00-07:00" into the daily quiet hours, which may wrap around midnight
+pub fn parse_quiet_hours(quiet_hours: &str) -> Result<QuietHours> {
+    let (start, end) = parse_time_window(quiet_hours)?;
+    if start == end {
+        return Err(anyhow!("the quiet hours {quiet_hours} should not be empty"));
+    }
+    Ok(QuietHours { start, end })
+}
+
+fn parse_time_window(window: &str) -> Result<((u8, u8), (u8, u8))> {
+    let re = Regex::new(r"^(\d{1,2}):(\d{2})-(\d{1,2}):(\d{2})$").unwrap();
+    let captures = re
+        .captures(window)
+        .ok_or_else(|| anyhow!("invalid time window {window}; valid examples: 09:00-18:00"))?;
+    let hour_minute = |hour: usize, minute: usize| -> Result<(u8, u8)> {
+        let (hour, minute) = (captures[hour].parse()?, captures[minute].parse()?);
+        Time::from_hms(hour, minute, 0).context(format!("invalid time window {window}"))?;
+        Ok((hour, minute))
+    };
+    Ok((hour_minute(1, 2)?, hour_minute(3, 4)?))
+}
+
+// returns the next fire of a periodic task after `after` inside its active hours, in the offset
+// of `after`; the fires of a day fall a whole number of periods after the start of its window, so
+// that the phase doesn't depend on when the task was scheduled
+pub fn next_in_window(
+    after: OffsetDateTime,
+    period: Duration,
+    window: &ActiveHours,
+) -> Option<OffsetDateTime> {
+    let start = Time::from_hms(window.start.0, window.start.1, 0).ok()?;
+    let end = Time::from_hms(window.end.0, window.end.1, 0).ok()?;
+    let period = TimeDuration::try_from(period).ok()?;
+    let active_day =
+        |date: Date| window.weekdays.is_empty() || window.weekdays.contains(&date.weekday());
+    let mut date = after.date();
+    for _ in 0..8 {
+        if active_day(date) {
+            let window_start = after.replace_date(date).replace_time(start);
+            // the first fire of the day is a period after the start of the window
+            let periods = match after - window_start {
+                elapsed if elapsed.is_negative() => 1,
+                elapsed => elapsed.whole_seconds() / period.whole_seconds().max(1) + 1,
+            };
+            let candidate = window_start.checked_add(period.checked_mul(periods as i32)?)?;
+            if candidate.date() == date && candidate.time() <= end {
+                return Some(candidate);
+            }
+        }
+        date = date.next_day()?;
+    }
+    None
+}
+
+// returns the first monthly occurrence strictly after `now`, in the offset of `now`
+pub fn next_monthly(
+    month_day: &MonthDay,
+    hour: u8,
+    minute: u8,
+    now: OffsetDateTime,
+) -> Option<OffsetDateTime> {
+    let time = Time::from_hms(hour, minute, 0).ok()?;
+    let (mut year, mut month) = (now.year(), now.month());
+    for _ in 0..12 * SEARCH_YEARS {
+        if let Some(date) = day_in_month(month_day, year, month) {
+            let candidate = now.replace_date(date).replace_time(time);
+            if candidate > now {
+                return Some(candidate);
+            }
+        }
+        if month == Month::December {
+            year += 1;
+        }
+        month = month.next();
+    }
+    None
+}
+
+// returns the first yearly occurrence strictly after `now`, in the offset of `now`
+pub fn next_yearly(
+    month: u8,
+    day: u8,
+    hour: u8,
+    minute: u8,
+    now: OffsetDateTime,
+) -> Option<OffsetDateTime> {
+    let time = Time::from_hms(hour, minute, 0).ok()?;
+    let month = Month::try_from(month).ok()?;
+    (now.year()..now.year() + SEARCH_YEARS)
+        .filter_map(|year| Date::from_calendar_date(year, month, day).ok())
+        .map(|date| now.replace_date(date).replace_time(time))
+        .find(|candidate| *candidate > now)
+}
+
+fn day_in_month(month_day: &MonthDay, year: i32, month: Month) -> Option<Date> {
+    match month_day {
+        MonthDay::Day(day) => Date::from_calendar_date(year, month, *day).ok(),
+        MonthDay::LastDay => (28..=31)
+            .rev()
+            .find_map(|day| Date::from_calendar_date(year, month, day).ok()),
+        MonthDay::NthWeekday(nth, weekday) => {
+            let first = Date::from_calendar_date(year, month, 1).ok()?;
+            let offset = (weekday.number_days_from_monday() + 7
+                - first.weekday().number_days_from_monday())
+                % 7;
+            let day = 1 + offset + (nth - 1) * 7;
+            Date::from_calendar_date(year, month, day).ok()
+        }
+    }
+}
+
+// the offset at startup; only used when the local time zone can't be found in the tzdb
 pub fn get_tzdiff() -> UtcOffset {
     TZDIFF.get_or_init(|| {
         UtcOffset::current_local_offset().expect("fail to get local timezone difference")
@@ -79,47 +455,146 @@ pub fn get_tzdiff() -> UtcOffset {
     offset
 }
 
+// the system time zone from $TZ or /etc/localtime, so that its offset is looked up again for
+// every moment and DST changes are followed without a restart
+pub fn get_local_timezone() -> Option<&'static Tz> {
+    *LOCAL_TIMEZONE.get_or_init(|| {
+        let timezone = std::env::var("TZ")
+            .ok()
+            .and_then(|name| timezones::get_by_name(name.trim_start_matches(':')))
+            .or_else(|| system::get_timezone().ok());
+        if timezone.is_none() {
+            warn!(
+                "fail to find the local time zone; would use the fixed offset {}",
+                get_tzdiff()
+            );
+        }
+        timezone
+    })
+}
+
+// now on the system clock, e.g. for fmn; the daemon goes by the clock of its scheduler
 pub fn get_local_now() -> OffsetDateTime {
-    OffsetDateTime::now_utc().to_offset(get_tzdiff())
+    get_local_now_on(&SystemClock)
+}
+
+// now on the given clock in the local time zone
+pub fn get_local_now_on(clock: &dyn Clock) -> OffsetDateTime {
+    in_timezone(clock.now_utc(), None)
+}
+
+// an IANA time zone name like Europe/Berlin, looked up in the bundled tzdb
+pub fn parse_timezone(name: &str) -> Result<&'static Tz> {
+    timezones::get_by_name(name)
+        .ok_or_else(|| anyhow!("unknown time zone {name}; valid examples: Europe/Berlin, UTC"))
+}
+
+// the same instant with the offset the given time zone, or the local one, uses at that moment
+pub fn in_timezone(moment: OffsetDateTime, timezone: Option<&Tz>) -> OffsetDateTime {
+    match timezone.or(get_local_timezone()) {
+        Some(timezone) => moment.to_timezone(timezone),
+        None => moment.to_offset(get_tzdiff()),
+    }
 }
 
-// only used for at
-pub fn parse_at(next_fire: &str) -> Result<OffsetDateTime> {
-    let re = Regex::new(r"(?P<hour>\d+):(?P<minute>\d+)").unwrap();
+// keeps the wall-clock of moment but picks the offset the time zone (the local one by default)
+// uses at that moment, e.g. when the next fire is computed across a DST change; times skipped by
+// the change are moved forward by an hour, and repeated ones take the first occurrence
+pub fn localize(moment: OffsetDateTime, timezone: Option<&Tz>) -> OffsetDateTime {
+    let timezone = match timezone.or(get_local_timezone()) {
+        Some(timezone) => timezone,
+        None => return moment,
+    };
+    let wall_clock = PrimitiveDateTime::new(moment.date(), moment.time());
+    wall_clock
+        .assume_timezone(timezone)
+        .take_first()
+        .unwrap_or_else(|| (wall_clock + TimeDuration::hours(1)).assume_timezone_utc(timezone))
+}
+
+// only used for at; accepts HH:MM, MM-DD HH:MM, YYYY-MM-DD HH:MM and ISO 8601 like
+// 2026-11-03T14:00:00+01:00. a bare HH:MM before now means tomorrow, while a date before now is
+// rejected
+pub fn parse_at(next_fire: &str, now: OffsetDateTime) -> Result<OffsetDateTime> {
+    parse_at_in(next_fire, now, None)
+}
+
+// same as parse_at, but the wall-clock is in the given time zone
+pub fn parse_at_in(
+    next_fire: &str,
+    now: OffsetDateTime,
+    timezone: Option<&Tz>,
+) -> Result<OffsetDateTime> {
+    let re = Regex::new(
+        r"^(?:(?:(?P<year>\d{4})-)?(?P<month>\d{1,2})-(?P<day>\d{1,2})[ T])?(?P<hour>\d{1,2}):(?P<minute>\d{1,2})(?::(?P<second>\d{1,2}))?(?P<offset>Z|[+-]\d{2}:?\d{2})?$",
+    )
+    .unwrap();
+    let captures = re.captures(next_fire.trim()).ok_or_else(|| {
+        anyhow!("invalid time! correct examples: 13:11, 11-03 14:00, 2026-11-03 14:00, 2026-11-03T14:00:00+01:00")
+    })?;
     let mut components = [0_u8; 3];
-    if let Some(captures) = re.captures(next_fire) {
-        for (i, component) in ["hour", "minute"].into_iter().enumerate() {
-            components[i] = captures
-                .name(component)
-                .map(|m| {
-                    // dbg!(component, m.as_str());
-                    m.as_str()
-                })
-                .ok_or_else(|| anyhow!("invalid time! correct examples: 13:11:04, 23:01:59"))?
-                .parse()
-                .context(format!("invalid {component}"))?;
-        }
-        let hour = components[0];
-        let minute = components[1];
-        let now = get_local_now();
-        let mut next_fire = now
-            .replace_millisecond(0)?
-            .replace_nanosecond(0)?
-            .replace_microsecond(0)?
-            .replace_hour(hour)?
-            .replace_minute(minute)?;
-
-        if now >= next_fire {
-            warn!(
-                "clock next_fire time {} shouldn't be in the past! would reschedule it tomorrow",
-                next_fire
-            );
-            next_fire = next_fire
-                .replace_day(now.day() + 1)
-                .expect("fail to reschedule the next day");
+    for (i, component) in ["hour", "minute", "second"].into_iter().enumerate() {
+        components[i] = captures
+            .name(component)
+            .map(|m| m.as_str())
+            .unwrap_or("0")
+            .parse()
+            .context(format!("invalid {component}"))?;
+    }
+    let time = Time::from_hms(components[0], components[1], components[2])
+        .context("invalid time of the day")?;
+    let offset = match captures.name("offset") {
+        Some(offset) => Some(parse_offset(offset.as_str())?),
+        None => None,
+    };
+    let now = in_timezone(now, timezone);
+    let today = match offset {
+        Some(offset) => now.to_offset(offset).date(),
+        None => now.date(),
+    };
+    let resolve = |date: Date| match offset {
+        Some(offset) => date.with_time(time).assume_offset(offset),
+        None => localize(date.with_time(time).assume_offset(now.offset()), timezone),
+    };
+
+    if let Some(month) = captures.name("month") {
+        let year = match captures.name("year") {
+            Some(year) => year.as_str().parse().context("invalid year")?,
+            None => today.year(),
+        };
+        let month = Month::try_from(month.as_str().parse::<u8>().context("invalid month")?)?;
+        let day = captures["day"].parse().context("invalid day")?;
+        let date = Date::from_calendar_date(year, month, day).context("invalid date")?;
+        let next_fire = resolve(date);
+        if next_fire <= now {
+            return Err(anyhow!(
+                "{} {} is in the past; please specify a future date",
+                date,
+                time
+            ));
         }
-        Ok(next_fire)
-    } else {
-        Err(anyhow!("fail to parse next_fire!"))
+        return Ok(next_fire);
+    }
+
+    let mut next_fire = resolve(today);
+    if now >= next_fire {
+        warn!(
+            "clock next_fire time {} shouldn't be in the past! would reschedule it tomorrow",
+            next_fire
+        );
+        next_fire = resolve(today.next_day().context("date out of range")?);
+    }
+    Ok(next_fire)
+}
+
+// Z, +01:00, -0530
+fn parse_offset(offset: &str) -> Result<UtcOffset> {
+    if offset == "Z" {
+        return Ok(UtcOffset::UTC);
     }
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits = offset[1..].replace(':', "");
+    let hours: i8 = digits[..2].parse().context("invalid offset hours")?;
+    let minutes: i8 = digits[2..].parse().context("invalid offset minutes")?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).context(format!("invalid offset {offset}"))
 }
@@ -1,11 +1,12 @@
 use std::io::{BufReader, BufWriter, Write};
 use std::net::TcpStream;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use log::{error, info};
 use serde_json::{to_string, Deserializer};
 
-use crate::comm::{ContextCommand, Request, Response};
+use crate::comm::{parse_duration, ContextCommand, DndCommand, Request, Response};
+use crate::dispatch::Dnd;
 use crate::task_manager::{Task, TaskManager};
 
 pub fn serve(stream: TcpStream, tm: &mut TaskManager) -> Result<()> {
@@ -18,9 +19,10 @@ pub fn serve(stream: TcpStream, tm: &mut TaskManager) -> Result<()> {
         tm.refresh_before();
         let response = {
             match request {
-                Request::Add(description, clock_type, image_path, sound_path) => {
-                    let mut task =
-                        Task::new(description, clock_type).with_context(tm.current_context());
+                Request::Add(description, clock_type, image_path, sound_path, options) => {
+                    let mut task = Task::new(description, clock_type)
+                        .with_context(tm.current_context())
+                        .with_options(*options);
                     if let Some(image_path) = image_path {
                         task.add_image(image_path);
                     }
@@ -43,10 +45,63 @@ pub fn serve(stream: TcpStream, tm: &mut TaskManager) -> Result<()> {
                         Response::RemoveSuccess
                     }
                 }
+                Request::Snooze(task_id, duration) => {
+                    // the request may not come from fmn
+                    let snoozed = parse_duration(&duration)
+                        .and_then(|duration| tm.snooze_task(task_id, duration));
+                    if let Err(e) = snoozed {
+                        error!("fail to snooze task: {}", e);
+                        Response::Fail(e.to_string())
+                    } else {
+                        Response::AddSuccess
+                    }
+                }
+                Request::Ack(task_id) => {
+                    if let Err(e) = tm.ack_task(task_id) {
+                        error!("fail to ack task: {}", e);
+                        Response::Fail(e.to_string())
+                    } else {
+                        Response::AckSuccess
+                    }
+                }
+                Request::Pause(task_id) => {
+                    if let Err(e) = tm.pause_task(task_id) {
+                        error!("fail to pause task: {}", e);
+                        Response::Fail(e.to_string())
+                    } else {
+                        Response::PauseSuccess
+                    }
+                }
+                Request::Resume(task_id) => {
+                    if let Err(e) = tm.resume_task(task_id) {
+                        error!("fail to resume task: {}", e);
+                        Response::Fail(e.to_string())
+                    } else {
+                        Response::ResumeSuccess
+                    }
+                }
                 Request::Show => Response::GetTasks(tm.get_tasks()),
+                Request::Upcoming { limit, window } => {
+                    let upcoming = window
+                        .map(|window| parse_duration(&window))
+                        .transpose()
+                        .and_then(|window| tm.upcoming(limit, window));
+                    match upcoming {
+                        Err(e) => {
+                            error!("fail to get upcoming tasks: {}", e);
+                            Response::Fail(e.to_string())
+                        }
+                        Ok(upcoming) => Response::Upcoming(upcoming),
+                    }
+                }
                 Request::ContextRequest(command) => handle_context_command(command, tm),
+                Request::Dnd(command) => handle_dnd_command(command, tm),
             }
         };
+        // persist before responding, so that the client sees its changes once it's answered
+        if let Err(e) = tm.refresh_after() {
+            error!("fail to flush changes to persistent storage: {e}");
+        }
         let serialized = to_string(&response).expect("fail to serialize response");
         match writer.write_all(serialized.as_bytes()) {
             Ok(_) => {
@@ -59,13 +114,33 @@ pub fn serve(stream: TcpStream, tm: &mut TaskManager) -> Result<()> {
         writer
             .flush()
             .context("fail to flush fmn-daemon tcp writer")?;
-        if let Err(e) = tm.refresh_after() {
-            error!("fail to flush changes to persistent storage: {e}");
-        }
     }
     Ok(())
 }
 
+fn handle_dnd_command(command: DndCommand, tm: &mut TaskManager) -> Response {
+    let dnd = match command {
+        DndCommand::On { duration: None } => tm.set_dnd(Dnd::On),
+        DndCommand::On {
+            duration: Some(duration),
+        } => match parse_duration(&duration).and_then(|duration| {
+            time::Duration::try_from(duration)
+                .ok()
+                .and_then(|duration| tm.local_now().checked_add(duration))
+                .ok_or_else(|| anyhow!("do not disturb for {:?} is too long", duration))
+        }) {
+            Err(e) => {
+                error!("fail to set do not disturb: {}", e);
+                return Response::Fail(e.to_string());
+            }
+            Ok(until) => tm.set_dnd(Dnd::Until(until)),
+        },
+        DndCommand::Off => tm.set_dnd(Dnd::Off),
+        DndCommand::Status => tm.dnd_status(),
+    };
+    Response::DndStatus(dnd)
+}
+
 fn handle_context_command(command: ContextCommand, tm: &mut TaskManager) -> Response {
     match command {
         ContextCommand::Define { context } => {
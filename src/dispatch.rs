@@ -0,0 +1,165 @@
+// the single place notifications go through, so that do-not-disturb applies to every clock
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::{Duration, OffsetDateTime, Time};
+
+use crate::comm::{in_timezone, localize};
+use crate::escalation::{EscalationStep, Urgency};
+use crate::notify::{desktop_notification, Sound};
+use crate::task_manager::Task;
+
+pub const SUMMARY: &str = "forget-me-not";
+
+// the daily quiet hours of the daemon, e.g. 22:00-07:00; may wrap around midnight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: (u8, u8), // hour, minute
+    pub end: (u8, u8),
+}
+
+// the do-not-disturb switched by `fmn dnd`, or the effective one when reported back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dnd {
+    #[default]
+    Off,
+    On, // until `fmn dnd off`
+    Until(OffsetDateTime),
+}
+
+// what happens to a task firing during do-not-disturb
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DuringDnd {
+    #[default]
+    Defer, // delivered in a summary when do-not-disturb ends
+    Drop,
+}
+
+pub struct Dispatcher {
+    quiet_hours: Option<QuietHours>,
+    dnd: Arc<Mutex<Dnd>>, // shared with the Scheduler for `fmn dnd`
+    deferred: Vec<String>,
+}
+
+impl QuietHours {
+    fn contains(&self, time: Time) -> bool {
+        let (start, end) = (self.start_time(), self.end_time());
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+
+    // the end of the quiet hours now is in
+    fn end_after(&self, now: OffsetDateTime) -> OffsetDateTime {
+        let now = in_timezone(now, None);
+        let mut end = now.replace_time(self.end_time());
+        if end <= now {
+            end += Duration::days(1);
+        }
+        localize(end, None)
+    }
+
+    fn start_time(&self) -> Time {
+        Time::from_hms(self.start.0, self.start.1, 0).expect("verified quiet hours")
+    }
+
+    fn end_time(&self) -> Time {
+        Time::from_hms(self.end.0, self.end.1, 0).expect("verified quiet hours")
+    }
+}
+
+impl fmt::Display for Dnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dnd::Off => write!(f, "off"),
+            Dnd::On => write!(f, "on"),
+            Dnd::Until(until) => {
+                let until = in_timezone(*until, None)
+                    .format(format_description!("[year]-[month]-[day] [hour]:[minute]"))
+                    .map_err(|_| fmt::Error)?;
+                write!(f, "on until {until}")
+            }
+        }
+    }
+}
+
+// the do-not-disturb in effect now, from either `fmn dnd` or the quiet hours
+pub fn effective_dnd(quiet_hours: Option<QuietHours>, dnd: Dnd, now: OffsetDateTime) -> Dnd {
+    let quiet_until = quiet_hours
+        .filter(|quiet_hours| quiet_hours.contains(in_timezone(now, None).time()))
+        .map(|quiet_hours| quiet_hours.end_after(now));
+    match (dnd, quiet_until) {
+        (Dnd::On, _) => Dnd::On,
+        (Dnd::Until(until), Some(quiet_until)) if until > now => Dnd::Until(until.max(quiet_until)),
+        (Dnd::Until(until), None) if until > now => Dnd::Until(until),
+        (_, Some(quiet_until)) => Dnd::Until(quiet_until),
+        _ => Dnd::Off,
+    }
+}
+
+impl Dispatcher {
+    pub fn new(quiet_hours: Option<QuietHours>, dnd: Arc<Mutex<Dnd>>) -> Self {
+        Dispatcher {
+            quiet_hours,
+            dnd,
+            deferred: vec![],
+        }
+    }
+
+    pub fn current(&self, now: OffsetDateTime) -> Dnd {
+        effective_dnd(self.quiet_hours, *self.dnd.lock().unwrap(), now)
+    }
+
+    // step is the one of the task's escalation in effect, if any
+    pub fn dispatch(
+        &mut self,
+        task: &Task,
+        body: &str,
+        step: Option<&EscalationStep>,
+        now: OffsetDateTime,
+    ) -> Result<()> {
+        if self.current(now) == Dnd::Off {
+            let sound = match (step, task.get_sound()) {
+                (Some(step), _) if !step.sound => Sound::None,
+                (_, Some(sound_path)) => Sound::File(sound_path),
+                (Some(_), None) => Sound::Default,
+                (None, None) => Sound::None,
+            };
+            let urgency = step.map_or(Urgency::Normal, |step| step.urgency);
+            return desktop_notification(SUMMARY, body, task.get_image(), sound, urgency);
+        }
+        match task.options.during_dnd {
+            DuringDnd::Defer => {
+                info!("defer \"{}\" until do-not-disturb ends", body);
+                if !self.deferred.iter().any(|deferred| deferred == body) {
+                    self.deferred.push(body.to_owned());
+                }
+            }
+            DuringDnd::Drop => info!("drop \"{}\" during do-not-disturb", body),
+        }
+        Ok(())
+    }
+
+    // delivers the deferred notifications in one summary once do-not-disturb has ended
+    pub fn flush(&mut self, now: OffsetDateTime) {
+        if self.deferred.is_empty() || self.current(now) != Dnd::Off {
+            return;
+        }
+        let body = format!(
+            "{} reminder(s) during do-not-disturb:\n{}",
+            self.deferred.len(),
+            self.deferred.join("\n")
+        );
+        self.deferred.clear();
+        if let Err(e) = desktop_notification(SUMMARY, &body, None, Sound::None, Urgency::Normal) {
+            error!("fail to send the do-not-disturb summary: {}", e);
+        }
+    }
+}
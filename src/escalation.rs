@@ -0,0 +1,86 @@
+// an escalation: the notifications of a fire grow more urgent until `fmn ack` or its end
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::comm::parse_duration;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical, // shown until dismissed
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Escalation {
+    pub steps: Vec<EscalationStep>, // ordered by their offsets
+    pub end: String,                // no more notifications this long after the fire, e.g. 30m
+}
+
+// how the notifications are sent from an offset after the fire on, e.g. 5m, until the next step
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EscalationStep {
+    pub after: String,
+    pub urgency: Urgency,
+    pub sound: bool,
+    pub every: Option<String>, // notified once if none
+}
+
+impl Escalation {
+    // a low urgency notification first, a normal one with a sound after 5 minutes, then a
+    // critical one every minute
+    pub fn profile(end: String) -> Self {
+        let step = |after: &str, urgency, sound, every: Option<&str>| EscalationStep {
+            after: after.to_owned(),
+            urgency,
+            sound,
+            every: every.map(str::to_owned),
+        };
+        Escalation {
+            steps: vec![
+                step("0s", Urgency::Low, false, None),
+                step("5m", Urgency::Normal, true, None),
+                step("6m", Urgency::Critical, true, Some("1m")),
+            ],
+            end,
+        }
+    }
+
+    // the step in effect at elapsed after the fire
+    pub fn step_at(&self, elapsed: Duration) -> Option<&EscalationStep> {
+        self.steps
+            .iter()
+            .take_while(|step| duration(&step.after) <= elapsed)
+            .last()
+    }
+
+    // the offset of the first notification strictly after elapsed, none once the escalation ends
+    pub fn next_after(&self, elapsed: Duration) -> Option<Duration> {
+        let end = duration(&self.end);
+        let offsets: Vec<Duration> = self
+            .steps
+            .iter()
+            .map(|step| duration(&step.after))
+            .collect();
+        for (i, step) in self.steps.iter().enumerate() {
+            let step_end = offsets.get(i + 1).copied().unwrap_or(end).min(end);
+            let next = match step.every.as_deref().map(duration) {
+                Some(every) if elapsed >= offsets[i] && !every.is_zero() => {
+                    let periods = (elapsed - offsets[i]).as_nanos() / every.as_nanos();
+                    offsets[i] + every * (periods + 1) as u32
+                }
+                _ => offsets[i],
+            };
+            if next > elapsed && next < step_end {
+                return Some(next);
+            }
+        }
+        None
+    }
+}
+
+fn duration(duration: &str) -> Duration {
+    parse_duration(duration).expect("this shall have been verified by the client side")
+}
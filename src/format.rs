@@ -1,12 +1,73 @@
+use std::collections::HashMap;
+use std::path::Path;
+
 use prettytable::{row, Table};
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+use crate::comm::in_timezone;
+use crate::task_manager::{Task, TaskID, TaskStatus};
 
-use crate::task_manager::Task;
+const UNTIL_FORMAT: &[FormatItem<'_>] = format_description!("[year]-[month]-[day] [hour]:[minute]");
 
-pub fn tabular_output(tasks: &Vec<Task>) -> String {
+// next_fires are those of the scheduled tasks; the others have no NEXT
+pub fn tabular_output(tasks: &Vec<Task>, next_fires: &HashMap<TaskID, OffsetDateTime>) -> String {
     let mut table = Table::new();
-    table.add_row(row!["ID", "TYPE", "DESCRIPTION"]);
+    table.add_row(row!["ID", "TYPE", "NEXT", "DESCRIPTION"]);
     for task in tasks {
-        table.add_row(row![task.task_id, task.clock_type, task.description]);
+        let mut clock_type = match &task.options.timezone {
+            Some(timezone) => format!("{} ({})", task.clock_type, timezone),
+            None => task.clock_type.to_string(),
+        };
+        if let Some(active_hours) = &task.options.active_hours {
+            clock_type.push_str(&format!(", {}", active_hours));
+        }
+        if let Some(count) = task.options.count {
+            clock_type.push_str(&format!(", {}/{} fired", task.fired, count));
+        }
+        if let Some(until) = task.options.until {
+            clock_type.push_str(&format!(
+                ", until {}",
+                until.format(UNTIL_FORMAT).expect("fail to format until")
+            ));
+        }
+        if !task.options.warn.is_empty() {
+            clock_type.push_str(&format!(", warn {}", task.options.warn.join(",")));
+        }
+        if let Some(escalation) = &task.options.escalation {
+            clock_type.push_str(&format!(", escalate {}", escalation.end));
+        }
+        if let Some(jitter) = &task.options.jitter {
+            clock_type.push_str(&format!(", jitter {}", jitter));
+        }
+        if let Some(calendar) = &task.options.skip_calendar {
+            let name = Path::new(calendar).file_name().unwrap_or_default();
+            clock_type.push_str(&format!(", skip {}", name.to_string_lossy()));
+        }
+        if task.status == TaskStatus::Paused {
+            clock_type.push_str(", paused");
+        }
+        let next_fire = next_fires
+            .get(&task.task_id)
+            .map_or_else(|| "-".to_owned(), |at| format_local(*at));
+        table.add_row(row![task.task_id, clock_type, next_fire, task.description]);
     }
     table.to_string()
 }
+
+pub fn upcoming_output(upcoming: &[(OffsetDateTime, Task)]) -> String {
+    let mut table = Table::new();
+    table.add_row(row!["NEXT", "ID", "DESCRIPTION"]);
+    for (at, task) in upcoming {
+        table.add_row(row![format_local(*at), task.task_id, task.description]);
+    }
+    table.to_string()
+}
+
+// a moment on the local wall clock, to the minute
+fn format_local(moment: OffsetDateTime) -> String {
+    in_timezone(moment, None)
+        .format(UNTIL_FORMAT)
+        .expect("fail to format a moment")
+}
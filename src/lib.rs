@@ -1,12 +1,20 @@
 #![forbid(unsafe_code)]
 
+pub mod calendar;
 pub mod client;
+pub mod clock;
 pub mod comm;
 pub mod daemon;
+pub mod dispatch;
+pub mod escalation;
 pub mod format;
+pub mod natural;
 pub mod notify;
+pub mod pomodoro;
+pub mod rrule;
 pub mod scheduler;
 pub mod task_manager;
+pub mod timer;
 
 use comm::get_local_now;
 use log::{debug, LevelFilter};
@@ -0,0 +1,229 @@
+// natural-language moments for `fmn add <description> when <expression>`
+//
+// expression := "in" amount unit (amount unit)*
+//             | [day] ["at"] [time]
+//             | time [day]
+// day        := "today" | "tomorrow" | ["this" | "next"] weekday
+// time       := H[:MM][am|pm] | "noon" | "midnight"
+//
+// a bare weekday may be today if the time hasn't passed, while "next" weekday never is;
+// a day without a time means 9:00, and a time without a day means its next occurrence
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use time::{Date, Duration, OffsetDateTime, Time, Weekday};
+use time_tz::Tz;
+
+use crate::comm::{in_timezone, localize, parse_duration, parse_weekdays};
+
+const DEFAULT_HOUR: u8 = 9;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Day {
+    Today,
+    Tomorrow,
+    Weekday(Weekday, bool), // weekday, strictly after today
+}
+
+// the wall-clock of an absolute moment is in the given time zone, the local one by default
+pub fn parse_natural(
+    expression: &str,
+    now: OffsetDateTime,
+    timezone: Option<&Tz>,
+) -> Result<OffsetDateTime> {
+    let now = in_timezone(now, timezone);
+    let tokens = tokenize(expression);
+    if tokens.is_empty() {
+        return Err(anyhow!("empty time expression"));
+    }
+    let moment = if tokens[0] == "in" {
+        now.checked_add(parse_relative(&tokens[1..])?)
+            .ok_or_else(|| anyhow!("\"{expression}\" is too far away"))?
+    } else {
+        localize(parse_absolute(&tokens, now)?, timezone)
+    };
+    if moment <= now {
+        return Err(anyhow!("\"{expression}\" is in the past"));
+    }
+    Ok(moment)
+}
+
+// lowercases and merges "6 pm" into "6pm"
+fn tokenize(expression: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    for token in expression.split_whitespace() {
+        let token = token.trim_end_matches(',').to_lowercase();
+        if matches!(token.as_str(), "am" | "pm") {
+            if let Some(last) = tokens.last_mut() {
+                last.push_str(&token);
+                continue;
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+// "2 hours", "an hour 30 minutes", "1h30m"
+fn parse_relative(tokens: &[String]) -> Result<Duration> {
+    if tokens.is_empty() {
+        return Err(anyhow!(
+            "expect an amount of time after \"in\", e.g. in 2 hours"
+        ));
+    }
+    let too_long = || anyhow!("\"in {}\" is too far away", tokens.join(" "));
+    let mut total = Duration::ZERO;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].as_str();
+        if let Ok(duration) = parse_duration(token) {
+            let duration = Duration::try_from(duration).map_err(|_| too_long())?;
+            total = total.checked_add(duration).ok_or_else(too_long)?;
+            i += 1;
+            continue;
+        }
+        let amount: i64 = match token {
+            "a" | "an" => 1,
+            _ => token
+                .parse()
+                .context(format!("invalid amount \"{token}\""))?,
+        };
+        let unit = tokens
+            .get(i + 1)
+            .ok_or_else(|| anyhow!("expect a unit after \"{token}\", e.g. minutes, hours"))?;
+        let seconds: i64 = match unit.trim_end_matches('s') {
+            "sec" | "second" => 1,
+            "min" | "minute" => 60,
+            "h" | "hr" | "hour" => 3600,
+            "day" => 86400,
+            "week" => 7 * 86400,
+            _ => return Err(anyhow!("unknown unit \"{unit}\"")),
+        };
+        total = amount
+            .checked_mul(seconds)
+            .and_then(|seconds| total.checked_add(Duration::seconds(seconds)))
+            .ok_or_else(too_long)?;
+        i += 2;
+    }
+    if total.is_zero() {
+        return Err(anyhow!("the amount of time should not be 0"));
+    }
+    Ok(total)
+}
+
+fn parse_absolute(tokens: &[String], now: OffsetDateTime) -> Result<OffsetDateTime> {
+    let mut day = None;
+    let mut time = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].as_str();
+        let parsed_day = match token {
+            "at" | "on" => {
+                i += 1;
+                continue;
+            }
+            "today" => Some(Day::Today),
+            "tomorrow" => Some(Day::Tomorrow),
+            "this" | "next" => {
+                let weekday = tokens
+                    .get(i + 1)
+                    .ok_or_else(|| anyhow!("expect a weekday after \"{token}\""))?;
+                i += 1;
+                Some(Day::Weekday(parse_weekday(weekday)?, token == "next"))
+            }
+            _ => match parse_weekday(token) {
+                Ok(weekday) => Some(Day::Weekday(weekday, false)),
+                Err(_) => None,
+            },
+        };
+        if let Some(parsed_day) = parsed_day {
+            if day.replace(parsed_day).is_some() {
+                return Err(anyhow!("the day is given more than once"));
+            }
+        } else {
+            let parsed_time = parse_time(token)
+                .context(format!("unknown word \"{token}\" in time expression"))?;
+            if time.replace(parsed_time).is_some() {
+                return Err(anyhow!("the time is given more than once"));
+            }
+        }
+        i += 1;
+    }
+
+    let today = now.date();
+    let date = match day {
+        Some(Day::Today) => today,
+        Some(Day::Tomorrow) => today.next_day().context("date out of range")?,
+        Some(Day::Weekday(weekday, strictly_after)) => {
+            let mut date = if strictly_after {
+                today.next_day().context("date out of range")?
+            } else {
+                today
+            };
+            while date.weekday() != weekday {
+                date = date.next_day().context("date out of range")?;
+            }
+            // "friday 9am" on a friday afternoon means the next friday
+            let time = time.unwrap_or_else(default_time);
+            if date == today && now.replace_time(time) <= now {
+                date += Duration::weeks(1);
+            }
+            date
+        }
+        None => {
+            let time = time.ok_or_else(|| anyhow!("expect a day or a time"))?;
+            next_date(today, time, now)
+        }
+    };
+    Ok(now
+        .replace_date(date)
+        .replace_time(time.unwrap_or_else(default_time)))
+}
+
+fn next_date(today: Date, time: Time, now: OffsetDateTime) -> Date {
+    if now.replace_time(time) > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+fn default_time() -> Time {
+    Time::from_hms(DEFAULT_HOUR, 0, 0).unwrap()
+}
+
+fn parse_weekday(token: &str) -> Result<Weekday> {
+    let weekdays = parse_weekdays(token)?;
+    if weekdays.len() != 1 {
+        return Err(anyhow!("expect exactly one weekday: {token}"));
+    }
+    Ok(weekdays[0])
+}
+
+// "6pm", "6:30pm", "18:30", "18", "noon", "midnight"
+fn parse_time(token: &str) -> Result<Time> {
+    match token {
+        "noon" => return Ok(Time::from_hms(12, 0, 0)?),
+        "midnight" => return Ok(Time::MIDNIGHT),
+        _ => {}
+    }
+    let re = Regex::new(r"^(?P<hour>\d{1,2})(?::(?P<minute>\d{2}))?(?P<meridiem>am|pm)?$").unwrap();
+    let captures = re
+        .captures(token)
+        .ok_or_else(|| anyhow!("invalid time; valid examples: 6pm, 6:30am, 18:30, noon"))?;
+    let mut hour: u8 = captures["hour"].parse()?;
+    let minute: u8 = captures
+        .name("minute")
+        .map(|m| m.as_str())
+        .unwrap_or("0")
+        .parse()?;
+    if let Some(meridiem) = captures.name("meridiem") {
+        if !(1..=12).contains(&hour) {
+            return Err(anyhow!("hour {hour} should be within 1-12 with am/pm"));
+        }
+        hour %= 12;
+        if meridiem.as_str() == "pm" {
+            hour += 12;
+        }
+    }
+    Time::from_hms(hour, minute, 0).context(format!("invalid time {token}"))
+}
@@ -2,25 +2,46 @@ use std::process::Command;
 
 use anyhow::{anyhow, Result};
 use log::{error, info};
-use notify_rust::Notification;
+use notify_rust::{Notification, Timeout};
+
+use crate::escalation::Urgency;
+
+const SOUND_NAME: &str = "message-new-instant"; // when asked for a sound without a sound file
+
+pub enum Sound<'a> {
+    None,
+    File(&'a str),
+    Default, // the one of the desktop
+}
 
 pub fn desktop_notification(
     summary: &str,
     body: &str,
     image_path: Option<&str>,
-    sound_path: Option<&str>,
+    sound: Sound,
+    urgency: Urgency,
 ) -> Result<()> {
     let mut notification = Notification::new();
     notification.summary(summary).body(body);
+    set_urgency(&mut notification, urgency);
+    if urgency == Urgency::Critical {
+        notification.timeout(Timeout::Never);
+    }
 
     if let Some(image_path) = image_path {
         info!("add image path hint: {}", &image_path);
         add_image(&mut notification, image_path);
     }
-    if let Some(sound_path) = sound_path {
-        info!("add sound path hint: {}", &sound_path);
-        if let Err(e) = play_sound(sound_path) {
-            error!("fail to play sound {}: {}", &sound_path, e);
+    match sound {
+        Sound::None => {}
+        Sound::File(sound_path) => {
+            info!("add sound path hint: {}", &sound_path);
+            if let Err(e) = play_sound(sound_path) {
+                error!("fail to play sound {}: {}", &sound_path, e);
+            }
+        }
+        Sound::Default => {
+            notification.sound_name(SOUND_NAME);
         }
     }
 
@@ -35,6 +56,15 @@ fn add_image(notification: &mut Notification, image_path: &str) {
     notification.image_path(image_path);
 }
 
+#[cfg(all(unix, not(target_os = "macos")))]
+fn set_urgency(notification: &mut Notification, urgency: Urgency) {
+    notification.urgency(match urgency {
+        Urgency::Low => notify_rust::Urgency::Low,
+        Urgency::Normal => notify_rust::Urgency::Normal,
+        Urgency::Critical => notify_rust::Urgency::Critical,
+    });
+}
+
 #[cfg(all(unix, not(target_os = "macos")))]
 fn play_sound(sound_path: &str) -> Result<()> {
     Command::new("paplay").arg(sound_path).spawn()?;
@@ -46,6 +76,11 @@ fn add_image(_notification: &mut Notification, _image_path: &str) {
     info!("macOS doesn't support attach images to notifications");
 }
 
+#[cfg(target_os = "macos")]
+fn set_urgency(_notification: &mut Notification, _urgency: Urgency) {
+    info!("macOS doesn't support the urgency of notifications");
+}
+
 #[cfg(target_os = "macos")]
 fn play_sound(sound_path: &str) -> Result<()> {
     Command::new("afplay").arg(sound_path).spawn()?;
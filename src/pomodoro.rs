@@ -0,0 +1,134 @@
+// a pomodoro: rounds of work separated by short breaks and followed by a long break, fired as
+// a single task which notifies at every change of phase
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+use crate::comm::parse_duration;
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Pomodoro {
+    pub start: OffsetDateTime,
+    // durations as given by `fmn pomodoro start`, e.g. 25m
+    pub work: String,
+    pub short_break: String,
+    pub long_break: String,
+    pub rounds: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work(u32), // the round, from 1
+    ShortBreak(u32),
+    LongBreak,
+}
+
+impl Pomodoro {
+    // every phase in order with the moment it ends, cut at the first phase which would end too far
+    // away to be represented
+    pub fn phases(&self) -> Vec<(Phase, OffsetDateTime)> {
+        let (work, short_break, long_break) = (
+            duration(&self.work),
+            duration(&self.short_break),
+            duration(&self.long_break),
+        );
+        let mut phases = vec![];
+        for round in 1..=self.rounds {
+            phases.push((Phase::Work(round), work));
+            if round < self.rounds {
+                phases.push((Phase::ShortBreak(round), short_break));
+            }
+        }
+        phases.push((Phase::LongBreak, long_break));
+        let mut end = self.start;
+        phases
+            .into_iter()
+            .map_while(|(phase, duration)| {
+                end = end.checked_add(duration)?;
+                Some((phase, end))
+            })
+            .collect()
+    }
+
+    // whether every phase ends at a moment which can be represented
+    pub fn is_representable(&self) -> bool {
+        self.phases()
+            .last()
+            .is_some_and(|(phase, _)| *phase == Phase::LongBreak)
+    }
+
+    // the phase at moment and when it ends, none before the start or after the end
+    pub fn phase_at(&self, moment: OffsetDateTime) -> Option<(Phase, OffsetDateTime)> {
+        if moment < self.start {
+            return None;
+        }
+        self.phases().into_iter().find(|(_, end)| *end > moment)
+    }
+
+    pub fn end(&self) -> OffsetDateTime {
+        self.phases().last().map_or(self.start, |(_, end)| *end)
+    }
+
+    // the first change of phase strictly after the given moment
+    pub fn next_change_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        self.phases()
+            .into_iter()
+            .map(|(_, end)| end)
+            .find(|end| *end > after)
+    }
+
+    // what to notify when the phase changes at the given moment
+    pub fn announce(&self, at: OffsetDateTime) -> String {
+        match self.phase_at(at) {
+            Some((Phase::Work(round), _)) => {
+                format!(
+                    "back to work, round {}/{} ({})",
+                    round, self.rounds, self.work
+                )
+            }
+            Some((Phase::ShortBreak(_), _)) => format!("take a short break ({})", self.short_break),
+            Some((Phase::LongBreak, _)) => format!("take a long break ({})", self.long_break),
+            None => "pomodoro done".to_owned(),
+        }
+    }
+
+    // the current phase and the time left in it
+    pub fn status(&self, now: OffsetDateTime) -> String {
+        match self.phase_at(now) {
+            Some((phase, end)) => {
+                let left = end - now;
+                let phase = match phase {
+                    Phase::Work(round) => format!("work, round {}/{}", round, self.rounds),
+                    Phase::ShortBreak(_) => "short break".to_owned(),
+                    Phase::LongBreak => "long break".to_owned(),
+                };
+                format!(
+                    "{}, {}m{:02}s left",
+                    phase,
+                    left.whole_minutes(),
+                    left.whole_seconds() % 60
+                )
+            }
+            None if now < self.start => "not started".to_owned(),
+            None => "done".to_owned(),
+        }
+    }
+}
+
+impl Display for Pomodoro {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pomodoro {}/{}/{} x{}",
+            self.work, self.short_break, self.long_break, self.rounds
+        )
+    }
+}
+
+fn duration(duration: &str) -> Duration {
+    parse_duration(duration)
+        .expect("this shall have been verified by the client side")
+        .try_into()
+        .expect("this shall have been verified by the client side")
+}
@@ -0,0 +1,505 @@
+// a subset of RFC 5545 recurrence rules:
+// FREQ(DAILY/WEEKLY/MONTHLY/YEARLY), INTERVAL, BYDAY, BYMONTHDAY, BYMONTH, BYSETPOS, COUNT, UNTIL
+// weeks always start on monday (WKST=MO)
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset, Weekday};
+use time_tz::Tz;
+
+use crate::comm::{localize, MONTH_NAMES, WEEKDAY_NAMES};
+use crate::task_manager::ordinal_suffix;
+
+// stop expanding a rule which never produces an occurrence, e.g. the 31st of february
+const MAX_PERIODS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    freq: Frequency,
+    interval: u32,
+    by_day: Vec<(Option<i8>, Weekday)>, // e.g. (Some(2), Tuesday) for 2TU, (None, Monday) for MO
+    by_month_day: Vec<i8>,              // negative values count from the end of the month
+    by_month: Vec<Month>,
+    by_set_pos: Vec<i16>,
+    count: Option<u32>,
+    until: Option<Until>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Until {
+    Date(Date), // inclusive, in the offset of dtstart
+    Moment(OffsetDateTime),
+}
+
+pub fn parse_rrule(rule: &str) -> Result<RRule> {
+    let rule = rule.trim();
+    let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+    let mut freq = None;
+    let mut parsed = RRule {
+        freq: Frequency::Daily,
+        interval: 1,
+        by_day: vec![],
+        by_month_day: vec![],
+        by_month: vec![],
+        by_set_pos: vec![],
+        count: None,
+        until: None,
+    };
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| anyhow!("invalid rule part {part}; expect KEY=VALUE"))?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                freq = Some(match value.to_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return Err(anyhow!("unsupported FREQ {value}")),
+                })
+            }
+            "INTERVAL" => {
+                parsed.interval = value.parse().context("invalid INTERVAL")?;
+                if parsed.interval == 0 {
+                    return Err(anyhow!("INTERVAL should not be 0"));
+                }
+            }
+            "COUNT" => {
+                let count = value.parse().context("invalid COUNT")?;
+                if count == 0 {
+                    return Err(anyhow!("COUNT should not be 0"));
+                }
+                parsed.count = Some(count);
+            }
+            "UNTIL" => parsed.until = Some(parse_until(value)?),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    parsed.by_day.push(parse_by_day(day)?);
+                }
+            }
+            "BYMONTHDAY" => {
+                for day in value.split(',') {
+                    let day: i8 = day.parse().context(format!("invalid BYMONTHDAY {day}"))?;
+                    if day == 0 || !(-31..=31).contains(&day) {
+                        return Err(anyhow!("BYMONTHDAY {day} should be within 1-31 or -31--1"));
+                    }
+                    parsed.by_month_day.push(day);
+                }
+            }
+            "BYMONTH" => {
+                for month in value.split(',') {
+                    let month: u8 = month.parse().context(format!("invalid BYMONTH {month}"))?;
+                    parsed.by_month.push(Month::try_from(month)?);
+                }
+            }
+            "BYSETPOS" => {
+                for pos in value.split(',') {
+                    let pos: i16 = pos.parse().context(format!("invalid BYSETPOS {pos}"))?;
+                    if pos == 0 || !(-366..=366).contains(&pos) {
+                        return Err(anyhow!("BYSETPOS {pos} should be within 1-366 or -366--1"));
+                    }
+                    parsed.by_set_pos.push(pos);
+                }
+            }
+            "WKST" if value.eq_ignore_ascii_case("MO") => {}
+            _ => return Err(anyhow!("unsupported rule part {part}")),
+        }
+    }
+    parsed.freq = freq.ok_or_else(|| anyhow!("FREQ is required, e.g. FREQ=WEEKLY;BYDAY=MO"))?;
+    if parsed.count.is_some() && parsed.until.is_some() {
+        return Err(anyhow!("COUNT and UNTIL should not be used together"));
+    }
+    let has_ordinal = parsed.by_day.iter().any(|(nth, _)| nth.is_some());
+    if has_ordinal && matches!(parsed.freq, Frequency::Daily | Frequency::Weekly) {
+        return Err(anyhow!(
+            "BYDAY with an ordinal only works with FREQ=MONTHLY/YEARLY"
+        ));
+    }
+    if parsed.freq == Frequency::Weekly && !parsed.by_month_day.is_empty() {
+        return Err(anyhow!("BYMONTHDAY should not be used with FREQ=WEEKLY"));
+    }
+    Ok(parsed)
+}
+
+fn parse_by_day(day: &str) -> Result<(Option<i8>, Weekday)> {
+    let re = Regex::new(r"^(?P<nth>[+-]?\d{1,2})?(?P<weekday>MO|TU|WE|TH|FR|SA|SU)$").unwrap();
+    let captures = re
+        .captures(day)
+        .ok_or_else(|| anyhow!("invalid BYDAY {day}; valid examples: MO, 2TU, -1FR"))?;
+    let index = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"]
+        .iter()
+        .position(|weekday| *weekday == &captures["weekday"])
+        .unwrap_or_default();
+    let weekday = (0..index).fold(Weekday::Sunday, |weekday, _| weekday.next());
+    let nth = match captures.name("nth") {
+        Some(nth) => {
+            let nth: i8 = nth.as_str().parse().context("invalid BYDAY ordinal")?;
+            if nth == 0 || !(-53..=53).contains(&nth) {
+                return Err(anyhow!(
+                    "BYDAY ordinal {nth} should be within 1-53 or -53--1"
+                ));
+            }
+            Some(nth)
+        }
+        None => None,
+    };
+    Ok((nth, weekday))
+}
+
+// accepts 20261220 (inclusive) or 20261220T093000Z
+fn parse_until(until: &str) -> Result<Until> {
+    let re = Regex::new(
+        r"^(?P<year>\d{4})(?P<month>\d{2})(?P<day>\d{2})(?:T(?P<hour>\d{2})(?P<minute>\d{2})(?P<second>\d{2})Z?)?$",
+    )
+    .unwrap();
+    let captures = re.captures(until).ok_or_else(|| {
+        anyhow!("invalid UNTIL {until}; valid examples: 20261220, 20261220T093000Z")
+    })?;
+    let date = Date::from_calendar_date(
+        captures["year"].parse()?,
+        Month::try_from(captures["month"].parse::<u8>()?)?,
+        captures["day"].parse()?,
+    )
+    .context(format!("invalid UNTIL {until}"))?;
+    match captures.name("hour") {
+        Some(hour) => {
+            let time = Time::from_hms(
+                hour.as_str().parse()?,
+                captures["minute"].parse()?,
+                captures["second"].parse()?,
+            )
+            .context(format!("invalid UNTIL {until}"))?;
+            Ok(Until::Moment(
+                PrimitiveDateTime::new(date, time).assume_offset(UtcOffset::UTC),
+            ))
+        }
+        None => Ok(Until::Date(date)),
+    }
+}
+
+impl RRule {
+    // all occurrences in order, starting from dtstart; they keep the wall clock of dtstart, at
+    // the offset the time zone uses on their date if one is given, e.g. across DST changes
+    pub fn occurrences(
+        &self,
+        dtstart: OffsetDateTime,
+        timezone: Option<&'static Tz>,
+    ) -> Occurrences<'_> {
+        let until = self.until.map(|until| match until {
+            Until::Date(date) => localize_in(
+                PrimitiveDateTime::new(date, Time::MIDNIGHT).assume_offset(dtstart.offset())
+                    + Duration::days(1)
+                    - Duration::nanoseconds(1),
+                timezone,
+            ),
+            Until::Moment(moment) => moment,
+        });
+        Occurrences {
+            rule: self,
+            dtstart,
+            timezone,
+            until,
+            period: 0,
+            pending: VecDeque::new(),
+            emitted: 0,
+        }
+    }
+
+    pub fn next_after(
+        &self,
+        dtstart: OffsetDateTime,
+        now: OffsetDateTime,
+        timezone: Option<&'static Tz>,
+    ) -> Option<OffsetDateTime> {
+        self.occurrences(dtstart, timezone)
+            .find(|occurrence| *occurrence > now)
+    }
+
+    pub fn summary(&self, dtstart: OffsetDateTime) -> String {
+        let unit = match self.freq {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        };
+        let mut summary = if self.interval == 1 {
+            format!("every {unit}")
+        } else {
+            format!("every {} {unit}s", self.interval)
+        };
+        if !self.by_month.is_empty() {
+            let months: Vec<&str> = self
+                .by_month
+                .iter()
+                .map(|month| MONTH_NAMES[*month as usize - 1])
+                .collect();
+            summary += &format!(" in {}", months.join(","));
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(|day| ordinal(*day)).collect();
+            summary += &format!(" on the {}", days.join(","));
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|(nth, weekday)| {
+                    let name = WEEKDAY_NAMES[weekday.number_days_from_sunday() as usize];
+                    match nth {
+                        Some(nth) => format!("{} {name}", ordinal(*nth)),
+                        None => name.to_owned(),
+                    }
+                })
+                .collect();
+            summary += &format!(" on {}", days.join(","));
+        }
+        if !self.by_set_pos.is_empty() {
+            let positions: Vec<String> = self.by_set_pos.iter().map(|pos| ordinal(*pos)).collect();
+            summary += &format!(" (only the {})", positions.join(","));
+        }
+        summary += &format!(" at {}:{:02}", dtstart.hour(), dtstart.minute());
+        if let Some(count) = self.count {
+            summary += &format!(", {count} times");
+        }
+        if let Some(until) = self.until {
+            let date = match until {
+                Until::Date(date) => date,
+                Until::Moment(moment) => moment.to_offset(dtstart.offset()).date(),
+            };
+            summary += &format!(
+                ", until {}-{:02}-{:02}",
+                date.year(),
+                date.month() as u8,
+                date.day()
+            );
+        }
+        summary
+    }
+
+    // dates produced by the k-th period counted from dtstart, sorted; None if out of range
+    fn expand(&self, start: Date, period: u32) -> Option<Vec<Date>> {
+        let steps = period as i64 * self.interval as i64;
+        let mut dates = match self.freq {
+            Frequency::Daily => {
+                let date = start.checked_add(Duration::days(steps))?;
+                let matches_day = self.by_month_day.is_empty() || self.matches_month_day(date);
+                let matches_weekday =
+                    self.by_day.is_empty() || self.by_day.iter().any(|(_, w)| *w == date.weekday());
+                if matches_day && matches_weekday {
+                    vec![date]
+                } else {
+                    vec![]
+                }
+            }
+            Frequency::Weekly => {
+                let monday = start
+                    .checked_sub(Duration::days(
+                        start.weekday().number_days_from_monday() as i64
+                    ))?
+                    .checked_add(Duration::weeks(steps))?;
+                (0..7)
+                    .filter_map(|i| monday.checked_add(Duration::days(i)))
+                    .filter(|date| {
+                        if self.by_day.is_empty() {
+                            date.weekday() == start.weekday()
+                        } else {
+                            self.by_day.iter().any(|(_, w)| *w == date.weekday())
+                        }
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let months = start.month() as i64 - 1 + steps;
+                let year = i32::try_from(start.year() as i64 + months / 12).ok()?;
+                let month = Month::try_from((months % 12) as u8 + 1).ok()?;
+                self.expand_month(year, month, start.day())?
+            }
+            Frequency::Yearly => {
+                let year = i32::try_from(start.year() as i64 + steps).ok()?;
+                if !self.by_month.is_empty() {
+                    let mut dates = vec![];
+                    for month in self.by_month.iter() {
+                        dates.extend(self.expand_month(year, *month, start.day())?);
+                    }
+                    dates
+                } else if !self.by_month_day.is_empty() {
+                    let mut dates = vec![];
+                    for month in (1..=12).filter_map(|m| Month::try_from(m).ok()) {
+                        dates.extend(self.expand_month(year, month, start.day())?);
+                    }
+                    dates
+                } else if !self.by_day.is_empty() {
+                    // ordinals count within the whole year
+                    let first = Date::from_calendar_date(year, Month::January, 1).ok()?;
+                    let days: Vec<Date> = (0..366)
+                        .filter_map(|i| first.checked_add(Duration::days(i)))
+                        .filter(|date| date.year() == year)
+                        .collect();
+                    self.select_by_day(&days)
+                } else {
+                    Date::from_calendar_date(year, start.month(), start.day())
+                        .ok()
+                        .into_iter()
+                        .collect()
+                }
+            }
+        };
+        if !self.by_month.is_empty() {
+            dates.retain(|date| self.by_month.contains(&date.month()));
+        }
+        dates.sort();
+        dates.dedup();
+        if !self.by_set_pos.is_empty() {
+            let len = dates.len() as i16;
+            let mut selected: Vec<Date> = self
+                .by_set_pos
+                .iter()
+                .filter_map(|pos| {
+                    let index = if *pos > 0 { pos - 1 } else { len + pos };
+                    dates.get(usize::try_from(index).ok()?).copied()
+                })
+                .collect();
+            selected.sort();
+            selected.dedup();
+            dates = selected;
+        }
+        Some(dates)
+    }
+
+    fn expand_month(&self, year: i32, month: Month, default_day: u8) -> Option<Vec<Date>> {
+        let first = Date::from_calendar_date(year, month, 1).ok()?;
+        if self.by_month_day.is_empty() && self.by_day.is_empty() {
+            return Some(
+                Date::from_calendar_date(year, month, default_day)
+                    .ok()
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        let days: Vec<Date> = (0..31)
+            .filter_map(|i| first.checked_add(Duration::days(i)))
+            .filter(|date| date.month() == month)
+            .collect();
+        // ordinals count within the whole month, before BYMONTHDAY narrows it down
+        let by_day = self.select_by_day(&days);
+        Some(
+            days.into_iter()
+                .filter(|date| self.by_month_day.is_empty() || self.matches_month_day(*date))
+                .filter(|date| self.by_day.is_empty() || by_day.contains(date))
+                .collect(),
+        )
+    }
+
+    fn matches_month_day(&self, date: Date) -> bool {
+        let len = (28..=31)
+            .rev()
+            .find(|day| Date::from_calendar_date(date.year(), date.month(), *day).is_ok())
+            .unwrap_or(31) as i8;
+        let day = date.day() as i8;
+        self.by_month_day
+            .iter()
+            .any(|d| *d == day || *d == day - len - 1)
+    }
+
+    fn select_by_day(&self, scope: &[Date]) -> Vec<Date> {
+        let mut selected = vec![];
+        for (nth, weekday) in self.by_day.iter() {
+            let matching: Vec<Date> = scope
+                .iter()
+                .filter(|date| date.weekday() == *weekday)
+                .copied()
+                .collect();
+            match nth {
+                None => selected.extend(matching),
+                Some(nth) => {
+                    let len = matching.len() as i8;
+                    let index = if *nth > 0 { nth - 1 } else { len + nth };
+                    if let Some(date) = usize::try_from(index).ok().and_then(|i| matching.get(i)) {
+                        selected.push(*date);
+                    }
+                }
+            }
+        }
+        selected
+    }
+}
+
+pub struct Occurrences<'a> {
+    rule: &'a RRule,
+    dtstart: OffsetDateTime,
+    timezone: Option<&'static Tz>,
+    until: Option<OffsetDateTime>,
+    period: u32,
+    pending: VecDeque<OffsetDateTime>,
+    emitted: u32,
+}
+
+impl Iterator for Occurrences<'_> {
+    type Item = OffsetDateTime;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(count) = self.rule.count {
+                if self.emitted >= count {
+                    return None;
+                }
+            }
+            if let Some(occurrence) = self.pending.pop_front() {
+                if occurrence < self.dtstart {
+                    continue;
+                }
+                // compared with UNTIL in absolute time
+                let occurrence = localize_in(occurrence, self.timezone);
+                if let Some(until) = self.until {
+                    if occurrence > until {
+                        return None;
+                    }
+                }
+                self.emitted += 1;
+                return Some(occurrence);
+            }
+            if self.period >= MAX_PERIODS {
+                return None;
+            }
+            let dates = self.rule.expand(self.dtstart.date(), self.period)?;
+            self.period += 1;
+            self.pending = dates
+                .into_iter()
+                .map(|date| self.dtstart.replace_date(date))
+                .collect();
+        }
+    }
+}
+
+// a moment at the offset of dtstart stays as is without a time zone
+fn localize_in(moment: OffsetDateTime, timezone: Option<&'static Tz>) -> OffsetDateTime {
+    match timezone {
+        Some(timezone) => localize(moment, Some(timezone)),
+        None => moment,
+    }
+}
+
+// 1 -> 1st, -1 -> last, -2 -> 2nd last
+fn ordinal<T: Into<i32>>(n: T) -> String {
+    let n: i32 = n.into();
+    if n == -1 {
+        return "last".to_owned();
+    }
+    let abs = n.unsigned_abs();
+    let suffix = ordinal_suffix(abs);
+    if n < 0 {
+        format!("{abs}{suffix} last")
+    } else {
+        format!("{abs}{suffix}")
+    }
+}
@@ -1,52 +1,133 @@
-use std::collections::HashMap;
-use std::time::Duration;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Result};
 use log::{debug, error, info, warn};
-use time::{OffsetDateTime, UtcOffset};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use time::macros::format_description;
+use time::{OffsetDateTime, Time};
+use time_tz::Tz;
 use tokio::runtime::{Builder, Runtime};
-use tokio::sync::broadcast;
-use tokio::sync::broadcast::error::RecvError;
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+use tokio::sync::{mpsc, oneshot};
 
-use crate::comm::{get_tzdiff, parse_duration};
-use crate::notify::desktop_notification;
-use crate::task_manager::{ClockType, Task, TaskID};
+use crate::calendar::{is_excluded, reload_changed};
+use crate::clock::{Clock, SystemClock};
+use crate::comm::{
+    get_local_timezone, get_tzdiff, in_timezone, localize, next_daily, next_in_window,
+    next_monthly, next_yearly, parse_cron, parse_duration,
+};
+use crate::dispatch::{effective_dnd, Dispatcher, Dnd, QuietHours};
+use crate::escalation::EscalationStep;
+use crate::rrule::parse_rrule;
+use crate::task_manager::{ClockType, MissedFire, Task, TaskID};
 
-const SUMMARY: &str = "forget-me-not";
-const CONSTANT_WAKUP_SECS: u64 = 30; // a task wake up periodically to check whether the time has
-                                     // passed, in case that the host goes to sleep
+const MAX_SLEEP_SECS: u64 = 30; // tokio timers use the monotonic clock, which stops while the host
+                                // sleeps, so the queue looks at the wall clock at least this often
+const CLOCK_JUMP_SECS: i64 = 5; // the wall clock running ahead or behind the monotonic one by more
+                                // than this means a suspend or a clock change
+const MAX_LATENESS_MINUTES: i64 = 1; // fires later than this are missed
+const MAX_LATE_FIRES: usize = 10; // at most this many missed fires of a task are notified late
+const MAX_CATCH_UP_STEPS: usize = 10000; // a bound on missed occurrences looked at for a task
+const MAX_RECENT_FIRES: usize = 32; // fired tasks remembered for snoozing
+const MAX_SKIPPED_DAYS: usize = 1000; // a task skipping more days in a row than this never fires
 
 pub struct Scheduler {
     task_sender: mpsc::Sender<SchedulerCommand>,
+    recent_fires: RecentFires,
+    quiet_hours: Option<QuietHours>,
+    dnd: Arc<Mutex<Dnd>>, // shared with the dispatcher of the inner scheduler
+    clock: Arc<dyn Clock>,
+}
+
+// daemon-wide settings
+#[derive(Debug, Default, Clone)]
+pub struct SchedulerConfig {
+    pub missed: MissedFire, // what to do with missed fires of tasks without their own policy
+    pub quiet_hours: Option<QuietHours>,
+    pub jitter_seed: Option<u64>, // the same jitter for every task, e.g. for tests
 }
 
 pub struct InnerScheduler {
-    cancel_channels: HashMap<TaskID, broadcast::Sender<TaskCommand>>,
-    tzdiff: UtcOffset,
+    // deadlines ordered by the earliest one first; an entry whose generation differs from the
+    // one of its task is left behind by a cancel or a re-add and is skipped
+    queue: BinaryHeap<Reverse<Deadline>>,
+    tasks: HashMap<TaskID, ScheduledTask>,
+    generation: u64,
+    missed: MissedFire, // for tasks without their own policy
+    recent_fires: RecentFires,
+    fire_hook: Option<FireHook>,
+    dispatcher: Dispatcher,
+    jitter_seed: Option<u64>,
+    clock: Arc<dyn Clock>,
 }
 
-#[derive(Debug)]
+// the most recently fired tasks first, shared with the Scheduler so that a task can be snoozed
+// even after it has left the task store
+type RecentFires = Arc<Mutex<VecDeque<Task>>>;
+
 enum SchedulerCommand {
     Add(Task),
     Cancel(Task),
+    Ack(TaskID, oneshot::Sender<bool>),
+    SetFireHook(FireHook),
+    NextFires(oneshot::Sender<HashMap<TaskID, OffsetDateTime>>),
 }
 
-#[derive(Clone, Debug)]
-enum TaskCommand {
-    Stop,
-    Cancel,
+// called with a task after it fires or misses fires, with its fire count and last fire updated
+pub type FireHook = Box<dyn FnMut(&Task) + Send>;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Deadline {
+    at: OffsetDateTime,
+    generation: u64,
+    task_id: TaskID,
+    nag: Option<u64>, // the unacknowledged fire to remind of again, for tasks until ack
+}
+
+// the first fire strictly after the given moment
+type NextFire = Box<dyn Fn(OffsetDateTime) -> Option<OffsetDateTime>>;
+
+struct ScheduledTask {
+    task: Task,
+    generation: u64,
+    next_after: NextFire,
+    unacked: Option<(u64, OffsetDateTime)>, // the latest fire waiting for `fmn ack`, fired at
+    done: bool,                             // no more fires, but it's still nagging
 }
 
 impl Scheduler {
     pub fn new() -> Self {
+        Self::with_config(SchedulerConfig::default())
+    }
+
+    pub fn with_config(config: SchedulerConfig) -> Self {
+        Self::with_clock(config, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(config: SchedulerConfig, clock: Arc<dyn Clock>) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let tzdiff = get_tzdiff();
+        let inner_clock = clock.clone();
+        let recent_fires = RecentFires::default();
+        let inner_recent_fires = recent_fires.clone();
+        let dnd = Arc::new(Mutex::new(Dnd::Off));
+        let dispatcher = Dispatcher::new(config.quiet_hours, dnd.clone());
+        // look up the local offset and time zone before any other thread is spawned
+        get_tzdiff();
+        get_local_timezone();
         std::thread::spawn(
             move || match Builder::new_current_thread().enable_all().build() {
                 Ok(rt) => {
-                    let mut inner = InnerScheduler::new(tzdiff);
+                    let mut inner = InnerScheduler::new(
+                        config.missed,
+                        inner_recent_fires,
+                        dispatcher,
+                        config.jitter_seed,
+                        inner_clock,
+                    );
                     inner.start(rt, receiver);
                 }
                 Err(e) => {
@@ -56,9 +137,31 @@ impl Scheduler {
         );
         Scheduler {
             task_sender: sender,
+            recent_fires,
+            quiet_hours: config.quiet_hours,
+            dnd,
+            clock,
         }
     }
 
+    pub fn clock(&self) -> Arc<dyn Clock> {
+        self.clock.clone()
+    }
+
+    // switches the ad-hoc do-not-disturb and returns the one in effect
+    pub fn set_dnd(&self, dnd: Dnd) -> Dnd {
+        *self.dnd.lock().unwrap() = dnd;
+        self.dnd_status()
+    }
+
+    pub fn dnd_status(&self) -> Dnd {
+        effective_dnd(
+            self.quiet_hours,
+            *self.dnd.lock().unwrap(),
+            self.clock.now_utc(),
+        )
+    }
+
     pub fn add_task(&mut self, task: Task) -> Result<()> {
         if self.check_inner_scheduler_crashed() {
             panic!("the inner scheduler has paniced!");
@@ -99,6 +202,55 @@ impl Scheduler {
         }
     }
 
+    pub fn set_fire_hook(&mut self, hook: FireHook) -> Result<()> {
+        if self.check_inner_scheduler_crashed() {
+            panic!("the inner scheduler has paniced!");
+        }
+        self.task_sender
+            .blocking_send(SchedulerCommand::SetFireHook(hook))
+            .map_err(|e| anyhow!("fail to send fire hook to inner scheduler: {}", e))
+    }
+
+    // stops re-notifying a task fired with --until-ack, false if it wasn't being re-notified
+    pub fn ack_task(&self, task: Task) -> Result<bool> {
+        if self.check_inner_scheduler_crashed() {
+            panic!("the inner scheduler has paniced!");
+        }
+        let task_id = task.task_id;
+        let (sender, receiver) = oneshot::channel();
+        self.task_sender
+            .blocking_send(SchedulerCommand::Ack(task_id.clone(), sender))
+            .map_err(|e| anyhow!("fail to send ack to inner scheduler: {}", e))?;
+        debug!("successfully send ack to inner scheduler: {}", task_id);
+        receiver
+            .blocking_recv()
+            .map_err(|e| anyhow!("fail to get ack from inner scheduler: {}", e))
+    }
+
+    // the next fire of every scheduled task, as queued by the inner scheduler
+    pub fn next_fires(&self) -> Result<HashMap<TaskID, OffsetDateTime>> {
+        if self.check_inner_scheduler_crashed() {
+            panic!("the inner scheduler has paniced!");
+        }
+        let (sender, receiver) = oneshot::channel();
+        self.task_sender
+            .blocking_send(SchedulerCommand::NextFires(sender))
+            .map_err(|e| anyhow!("fail to ask the inner scheduler for next fires: {}", e))?;
+        receiver
+            .blocking_recv()
+            .map_err(|e| anyhow!("fail to get next fires from inner scheduler: {}", e))
+    }
+
+    // the most recently fired task whose id starts with task_id
+    pub fn recently_fired(&self, task_id: &str) -> Option<Task> {
+        self.recent_fires
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|task| task.task_id.starts_with(task_id))
+            .cloned()
+    }
+
     fn check_inner_scheduler_crashed(&self) -> bool {
         self.task_sender.is_closed()
     }
@@ -111,238 +263,726 @@ impl Default for Scheduler {
 }
 
 impl InnerScheduler {
-    fn new(tzdiff: UtcOffset) -> Self {
+    fn new(
+        missed: MissedFire,
+        recent_fires: RecentFires,
+        dispatcher: Dispatcher,
+        jitter_seed: Option<u64>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         InnerScheduler {
-            cancel_channels: HashMap::new(),
-            tzdiff,
+            queue: BinaryHeap::new(),
+            tasks: HashMap::new(),
+            generation: 0,
+            missed,
+            recent_fires,
+            fire_hook: None,
+            dispatcher,
+            jitter_seed,
+            clock,
         }
     }
 
     fn start(&mut self, rt: Runtime, mut task_receiver: mpsc::Receiver<SchedulerCommand>) {
         rt.block_on(async {
-            while let Some(scheduler_command) = task_receiver.recv().await {
-                match scheduler_command {
-                    SchedulerCommand::Add(task) => {
-                        self.add_task(task);
-                    }
-                    SchedulerCommand::Cancel(task_id) => {
-                        if let Err(e) = self.cancel_task(task_id) {
-                            // no active receivers
-                            // https://docs.rs/tokio/latest/tokio/sync/broadcast/error/struct.SendError.html
-                            error!("fail to cancel task: {}", e);
+            let mut last_wake = self.wake();
+            loop {
+                tokio::select! {
+                    biased;
+
+                    scheduler_command = task_receiver.recv() => match scheduler_command {
+                        Some(SchedulerCommand::Add(task)) => {
+                            self.add_task(task);
+                        }
+                        Some(SchedulerCommand::Cancel(task)) => {
+                            if let Err(e) = self.cancel_task(task) {
+                                error!("fail to cancel task: {}", e);
+                            }
+                        }
+                        Some(SchedulerCommand::Ack(task_id, sender)) => {
+                            // the daemon may have stopped waiting
+                            let _ = sender.send(self.ack_task(&task_id));
                         }
+                        Some(SchedulerCommand::SetFireHook(hook)) => {
+                            self.fire_hook = Some(hook);
+                        }
+                        Some(SchedulerCommand::NextFires(sender)) => {
+                            // the daemon may have stopped waiting
+                            let _ = sender.send(self.next_fires());
+                        }
+                        None => return,
+                    },
+                    _ = self.clock.sleep(self.time_to_next_fire()) => {}
+                }
+                let wake = self.wake();
+                if let Some(jump) = clock_jump(last_wake, wake) {
+                    warn!(
+                        "the wall clock jumped by {}; the host may have been suspended",
+                        jump
+                    );
+                    // deadlines passed by a forward jump are caught up by fire_due
+                    if jump.is_negative() {
+                        self.reschedule(wake.1, |_| true);
                     }
                 }
+                let calendars = reload_changed();
+                if !calendars.is_empty() {
+                    info!("exclusion calendars changed: {:?}", calendars);
+                    self.reschedule(wake.1, |task| skips_any(task, &calendars));
+                }
+                last_wake = wake;
+                self.fire_due(wake.1);
+                self.dispatcher.flush(wake.1);
             }
         });
     }
 
+    // the monotonic and the wall clock time
+    fn wake(&self) -> (Instant, OffsetDateTime) {
+        (self.clock.instant(), self.clock.now_utc())
+    }
+
     pub fn add_task(&mut self, task: Task) {
-        // we finally need to insert task_id as a key so it's fine to clone here
-        let task_id = task.task_id.clone();
-        let clock_type = task.clock_type.clone();
-        info!("add new clock task: {}, {}", task_id, clock_type);
-        let (sender, receiver) = broadcast::channel(1);
-        // enter the tokio rt context so that we can use tokio::spawn
-        let (hour_diff, minute_diff, _) = self.tzdiff.as_hms();
-        match clock_type {
-            ClockType::Once(next_fire) => {
-                let sender = sender.clone();
-                let date = next_fire.date();
-                let hour = next_fire.hour();
-                let minute = next_fire.minute();
-                let now = OffsetDateTime::now_utc();
-                let duration = (next_fire - now).whole_seconds() as u64;
-                let period = CONSTANT_WAKUP_SECS.min(duration);
-                tokio::spawn(period_do(
-                    Duration::from_secs(period),
-                    receiver,
-                    move || info!("once task at {} is removed!", next_fire),
-                    move || {
-                        let now = OffsetDateTime::now_utc();
-                        let now_hour = now.hour() as i8 + hour_diff;
-                        let now_minute = now.minute() as i8 + minute_diff;
-                        let now_date = now.date();
-                        if now_date == date && (now_hour as u8, now_minute as u8) >= (hour, minute)
-                        {
-                            if now_minute - minute as i8 <= 1 {
-                                info!(
-                                    "a once clock at {}:{} and description {} fire!",
-                                    hour, minute, &task.description
-                                );
-                                if let Err(e) = desktop_notification(
-                                    SUMMARY,
-                                    &task.description,
-                                    task.get_image(),
-                                    task.get_sound(),
-                                ) {
-                                    error!("fail to send de notification: {}", e);
-                                }
-                            }
-                            sender
-                                .send(TaskCommand::Stop)
-                                .expect("fail to stop after de notify err");
-                        }
-                    },
-                ))
+        info!("add new clock task: {}, {}", task.task_id, task.clock_type);
+        self.schedule(task, self.clock.now_utc());
+    }
+
+    pub fn cancel_task(&mut self, task: Task) -> Result<()> {
+        // the deadlines left in the queue are skipped once the task is gone
+        match self.tasks.remove(&task.task_id) {
+            Some(scheduled) => info!(
+                "{} task {} is removed!",
+                scheduled.task.clock_type, scheduled.task.description
+            ),
+            None => warn!("fail to find scheduled task for task id: {}", task.task_id),
+        }
+        Ok(())
+    }
+
+    // whether the task was being re-notified
+    pub fn ack_task(&mut self, task_id: &TaskID) -> bool {
+        let (acked, done) = match self.tasks.get_mut(task_id) {
+            Some(scheduled) => {
+                info!("task {} is acknowledged", scheduled.task.description);
+                (scheduled.unacked.take().is_some(), scheduled.done)
             }
-            ClockType::Period(period) => {
-                let duration = parse_duration(&period)
-                    .expect("this shall have been verified by the client side");
-                tokio::spawn(period_clock(task, duration, sender.clone(), receiver))
+            None => {
+                warn!("fail to find scheduled task for task id: {}", task_id);
+                (false, false)
             }
-            ClockType::OncePerDay(hour, minute) => {
-                let sender = sender.clone();
-                tokio::spawn(period_do(
-                    Duration::from_secs(60),
-                    receiver,
-                    move || {
-                        info!("everyday task at {}:{} is removed!", hour, minute);
-                    },
-                    move || {
-                        let now = OffsetDateTime::now_utc();
-                        let now_hour = now.hour() as i8 + hour_diff;
-                        let now_minute = now.minute() as i8 + minute_diff;
-                        if (now_hour as u8, now_minute as u8) == (hour, minute) {
-                            info!(
-                                "a clock at {}:{} everyday and description {} fire!",
-                                hour, minute, &task.description
-                            );
-                            if let Err(e) = desktop_notification(
-                                SUMMARY,
-                                &task.description,
-                                task.get_image(),
-                                task.get_sound(),
-                            ) {
-                                error!("fail to send de notification: {}", e);
-                                sender
-                                    .send(TaskCommand::Stop)
-                                    .expect("fail to stop after de notify err");
-                            }
-                        }
-                    },
-                ))
+        };
+        if done {
+            self.tasks.remove(task_id);
+        }
+        acked
+    }
+
+    fn schedule(&mut self, task: Task, now: OffsetDateTime) {
+        let (first_fire, next_after) = timing(&task, now, self.jitter_seed);
+        // the occurrences since the last fire, e.g. while the daemon was down, are handled by
+        // fire_due under the task's MissedFire policy
+        let first_fire = match task.last_fired.filter(|last_fired| *last_fired < now) {
+            Some(last_fired) => next_after(last_fired)
+                .filter(|missed| *missed <= now)
+                .or(first_fire),
+            None => first_fire,
+        };
+        let first_fire = match first_fire.filter(|_| task.remaining_fires() != Some(0)) {
+            Some(first_fire) => first_fire,
+            None => {
+                error!(
+                    "{} task {} would never fire",
+                    task.clock_type, task.description
+                );
+                return;
             }
         };
-        self.cancel_channels.insert(task_id, sender);
+        self.generation += 1;
+        self.queue.push(Reverse(Deadline {
+            at: first_fire,
+            generation: self.generation,
+            task_id: task.task_id.clone(),
+            nag: None,
+        }));
+        self.tasks.insert(
+            task.task_id.clone(),
+            ScheduledTask {
+                task,
+                generation: self.generation,
+                next_after,
+                unacked: None,
+                done: false,
+            },
+        );
     }
 
-    pub fn cancel_task(&mut self, task: Task) -> Result<()> {
-        let task_id = task.task_id;
-        if let Some(sender) = self.cancel_channels.get(&task_id) {
-            if let Err(e) = sender
-                .send(TaskCommand::Cancel)
-                .context("fail to send stop to clock")
-            {
-                // no active receivers
-                return Err(anyhow!("fail to send cancel task: {}", e));
+    // computes the deadlines of the matching tasks again from now, e.g. of every task after the
+    // wall clock moved backwards; the deadlines left in the queue are skipped by their generation
+    fn reschedule<F>(&mut self, now: OffsetDateTime, matches: F)
+    where
+        F: Fn(&Task) -> bool,
+    {
+        let task_ids: Vec<TaskID> = self
+            .tasks
+            .values()
+            .filter(|scheduled| matches(&scheduled.task))
+            .map(|scheduled| scheduled.task.task_id.clone())
+            .collect();
+        for task_id in task_ids {
+            let scheduled = self
+                .tasks
+                .remove(&task_id)
+                .expect("the task ids are collected just now");
+            let unacked = scheduled.unacked;
+            if scheduled.done {
+                self.tasks.insert(task_id.clone(), scheduled);
+            } else {
+                self.schedule(scheduled.task, now);
+            }
+            // keep nagging about an unacknowledged fire
+            if let Some(scheduled) = self.tasks.get_mut(&task_id) {
+                scheduled.unacked = unacked;
+                let next_nag = unacked.and_then(|(nag, fired_at)| {
+                    Some((nag, next_nag(&scheduled.task, fired_at, now)?))
+                });
+                if let Some((nag, at)) = next_nag {
+                    self.queue.push(Reverse(Deadline {
+                        at,
+                        generation: scheduled.generation,
+                        task_id,
+                        nag: Some(nag),
+                    }));
+                }
             }
-        } else {
-            warn!("fail to find sender channel for task id: {}", task_id);
         }
-        Ok(())
     }
-}
 
-async fn period_clock(
-    task: Task,
-    period: Duration,
-    sender: broadcast::Sender<TaskCommand>,
-    receiver: broadcast::Receiver<TaskCommand>,
-) {
-    period_do(
-        period,
-        receiver,
-        || {
-            info!("periodic task with period {:?} is removed!", period);
-        },
-        || {
-            info!(
-                "a clock with period {} and description {} fire!",
-                period.as_secs(),
-                &task.description
-            );
-            if let Err(e) = desktop_notification(
-                SUMMARY,
-                &task.description,
-                task.get_image(),
-                task.get_sound(),
-            ) {
+    // the earliest live deadline of every task which still fires
+    fn next_fires(&self) -> HashMap<TaskID, OffsetDateTime> {
+        let mut next_fires = HashMap::new();
+        for Reverse(deadline) in &self.queue {
+            let live = self.tasks.get(&deadline.task_id).is_some_and(|scheduled| {
+                scheduled.generation == deadline.generation && !scheduled.done
+            });
+            if !live || deadline.nag.is_some() {
+                continue;
+            }
+            next_fires
+                .entry(deadline.task_id.clone())
+                .and_modify(|at: &mut OffsetDateTime| *at = (*at).min(deadline.at))
+                .or_insert(deadline.at);
+        }
+        next_fires
+    }
+
+    // how long to sleep until the earliest deadline, but no longer than MAX_SLEEP_SECS
+    fn time_to_next_fire(&self) -> Duration {
+        let max_sleep = Duration::from_secs(MAX_SLEEP_SECS);
+        match self.queue.peek() {
+            Some(Reverse(deadline)) => (deadline.at - self.clock.now_utc())
+                .try_into()
+                .unwrap_or(Duration::ZERO)
+                .min(max_sleep),
+            None => max_sleep,
+        }
+    }
+
+    // fires every task whose deadline has passed; the occurrences which are more than
+    // MAX_LATENESS_MINUTES late are missed and handled by the task's MissedFire policy
+    fn fire_due(&mut self, now: OffsetDateTime) {
+        while let Some(Reverse(deadline)) = self.queue.peek() {
+            if deadline.at > now {
+                break;
+            }
+            let Reverse(deadline) = self.queue.pop().unwrap();
+            let task_id = deadline.task_id;
+            let scheduled = match self.tasks.get_mut(&task_id) {
+                Some(scheduled) if scheduled.generation == deadline.generation => scheduled,
+                _ => continue,
+            };
+            if let Some(nag) = deadline.nag {
+                let fired_at = match scheduled.unacked {
+                    Some((unacked, fired_at)) if unacked == nag => fired_at,
+                    _ => continue,
+                };
+                let task = &scheduled.task;
+                if let Err(e) = nag_again(&mut self.dispatcher, task, fired_at, now) {
+                    error!("fail to send de notification: {}", e);
+                }
+                match next_nag(task, fired_at, now) {
+                    Some(at) => self.queue.push(Reverse(Deadline {
+                        at,
+                        task_id,
+                        ..deadline
+                    })),
+                    // the escalation has ended
+                    None => {
+                        info!("stop notifying the task {} again", task.description);
+                        scheduled.unacked = None;
+                        if scheduled.done {
+                            self.tasks.remove(&task_id);
+                        }
+                    }
+                }
+                continue;
+            }
+            let task = &scheduled.task;
+            let (mut missed, next_fire) = occurrences_until(scheduled, deadline.at, now);
+            let on_time = missed
+                .back()
+                .filter(|last| now - **last <= time::Duration::minutes(MAX_LATENESS_MINUTES))
+                .copied();
+            if on_time.is_some() {
+                missed.pop_back();
+            }
+            let policy = task.options.missed.unwrap_or(self.missed);
+            let mut fires: Vec<(OffsetDateTime, bool)> = match policy {
+                MissedFire::Late => missed
+                    .iter()
+                    // a pre-alert is pointless once its fire has passed
+                    .filter(|at| pre_alert_of(task, **at).is_none_or(|fire| fire > now))
+                    .map(|at| (*at, true))
+                    .collect(),
+                MissedFire::Latest if on_time.is_none() => {
+                    missed.back().map(|at| (*at, true)).into_iter().collect()
+                }
+                _ => vec![],
+            };
+            if !missed.is_empty() {
+                warn!(
+                    "the {} task {} missed {} fire(s) since {}; policy: {:?}",
+                    task.clock_type,
+                    task.description,
+                    missed.len(),
+                    missed[0],
+                    policy
+                );
+            }
+            fires.extend(on_time.map(|at| (at, false)));
+            if let Some(remaining) = task.remaining_fires() {
+                fires.truncate(remaining as usize);
+            }
+            let fired = !fires.is_empty();
+            scheduled.task.last_fired = on_time.or(missed.back().copied());
+            let task = &scheduled.task;
+            if fired {
+                let mut recent_fires = self.recent_fires.lock().unwrap();
+                recent_fires.retain(|t| t.task_id != task_id);
+                recent_fires.push_front(task.clone());
+                recent_fires.truncate(MAX_RECENT_FIRES);
+            }
+            let result = fires
+                .iter()
+                .try_for_each(|(at, missed)| fire(&mut self.dispatcher, task, *at, *missed, now));
+            scheduled.task.fired += fires.len() as u32;
+            if fired || !missed.is_empty() {
+                if let Some(hook) = self.fire_hook.as_mut() {
+                    hook(&scheduled.task);
+                }
+            }
+            let task = &scheduled.task;
+            if let Err(e) = result {
                 error!("fail to send de notification: {}", e);
-                sender
-                    .send(TaskCommand::Stop)
-                    .expect("fail to stop after de notify err");
+                self.tasks.remove(&task_id);
+                continue;
             }
-        },
-    )
-    .await;
+            // a new fire replaces the one being nagged about; a pre-alert isn't nagged about
+            let fired_main = fires
+                .iter()
+                .any(|(at, _)| pre_alert_of(task, *at).is_none());
+            if let Some(at) = next_nag(task, now, now).filter(|_| fired_main) {
+                self.generation += 1;
+                scheduled.unacked = Some((self.generation, now));
+                self.queue.push(Reverse(Deadline {
+                    at,
+                    generation: deadline.generation,
+                    task_id: task_id.clone(),
+                    nag: Some(self.generation),
+                }));
+            }
+            let next_fire = next_fire.filter(|_| task.remaining_fires() != Some(0));
+            match next_fire {
+                Some(next_fire) => self.queue.push(Reverse(Deadline {
+                    at: next_fire,
+                    task_id,
+                    ..deadline
+                })),
+                None if scheduled.unacked.is_some() => scheduled.done = true,
+                None => {
+                    info!("{} task {} is done", task.clock_type, task.description);
+                    self.tasks.remove(&task_id);
+                }
+            }
+        }
+    }
 }
 
-async fn period_do<F1, F2>(
-    period: Duration,
-    mut receiver: broadcast::Receiver<TaskCommand>,
-    after_cancel: F1,
-    after_wake: F2,
-) where
-    F1: Fn(),
-    F2: Fn(),
-{
-    loop {
-        tokio::select! {
-            biased;
-
-            val = receiver.recv() => {
-                if is_removed(val) {
-                    after_cancel();
-                    return
+// the occurrences from deadline up to now, keeping the last MAX_LATE_FIRES of them, and the
+// first one after now
+fn occurrences_until(
+    scheduled: &ScheduledTask,
+    deadline: OffsetDateTime,
+    now: OffsetDateTime,
+) -> (VecDeque<OffsetDateTime>, Option<OffsetDateTime>) {
+    let mut occurrences = VecDeque::from([deadline]);
+    let mut next_fire = (scheduled.next_after)(deadline);
+    for _ in 0..MAX_CATCH_UP_STEPS {
+        match next_fire {
+            Some(next) if next <= now => {
+                if occurrences.len() == MAX_LATE_FIRES {
+                    occurrences.pop_front();
                 }
+                occurrences.push_back(next);
+                next_fire = (scheduled.next_after)(next);
             }
-            _ = sleep(period) => {
-                after_wake();
-            }
+            _ => return (occurrences, next_fire),
+        }
+    }
+    (occurrences, (scheduled.next_after)(now))
+}
+
+// how often a task fired with --until-ack is notified again
+fn nag_interval(task: &Task) -> Option<time::Duration> {
+    let interval = task.options.until_ack.as_deref()?;
+    let interval =
+        parse_duration(interval).expect("this shall have been verified by the client side");
+    time::Duration::try_from(interval).ok()
+}
+
+// when to notify a fire at fired_at and not acknowledged yet again after now; none once its
+// escalation has ended
+fn next_nag(task: &Task, fired_at: OffsetDateTime, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    match &task.options.escalation {
+        Some(escalation) => {
+            let elapsed = (now - fired_at).try_into().unwrap_or(Duration::ZERO);
+            escalation
+                .next_after(elapsed)
+                .and_then(|offset| time::Duration::try_from(offset).ok())
+                .and_then(|offset| fired_at.checked_add(offset))
         }
+        None => nag_interval(task).and_then(|interval| now.checked_add(interval)),
     }
 }
 
-// async fn once_clock(
-//     task: Task,
-//     next_fire: OffsetDateTime,
-//     mut receiver: broadcast::Receiver<TaskCommand>,
-// ) {
-//     let now = OffsetDateTime::now_utc();
-//     if now >= next_fire {
-//         error!(
-//             "clock next_fire time {} shouldn't be in the past! would reschedule it tomorrow",
-//             next_fire
-//         );
-//         return;
-//     }
-//     let duration = (next_fire - now).unsigned_abs();
-//     tokio::select! {
-//         val = receiver.recv() => {
-//             if is_canceled(val) {
-//                 info!("once clock with next_fire {:?} is removed!", next_fire);
-//                 return
-//             }
-//         }
-//         _ = sleep(duration) => {
-//             info!("a clock fire!");
-//             if let Err(e) = desktop_notification(SUMMARY, &task.description, task.get_image(), task.get_sound()) {
-//                 error!("fail to send notification: {}", e);
-//             }
-//         }
-//     }
-// }
-
-fn is_removed(val: std::result::Result<TaskCommand, RecvError>) -> bool {
-    match val {
-        Ok(command) => match command {
-            TaskCommand::Stop => true,
-            TaskCommand::Cancel => true,
-        },
-        Err(e) => {
-            error!("fail to receive command: {}", e);
-            true
+// the step of a task's escalation in effect at now for a fire at fired_at
+fn escalation_step(
+    task: &Task,
+    fired_at: OffsetDateTime,
+    now: OffsetDateTime,
+) -> Option<&EscalationStep> {
+    let elapsed = (now - fired_at).try_into().unwrap_or(Duration::ZERO);
+    task.options.escalation.as_ref()?.step_at(elapsed)
+}
+
+fn nag_again(
+    dispatcher: &mut Dispatcher,
+    task: &Task,
+    fired_at: OffsetDateTime,
+    now: OffsetDateTime,
+) -> Result<()> {
+    info!(
+        "remind of the unacknowledged task {} again",
+        task.description
+    );
+    let body = format!(
+        "{} (not acknowledged yet; fmn ack {})",
+        task.description, task.task_id
+    );
+    dispatcher.dispatch(task, &body, escalation_step(task, fired_at, now), now)
+}
+
+fn fire(
+    dispatcher: &mut Dispatcher,
+    task: &Task,
+    at: OffsetDateTime,
+    missed: bool,
+    now: OffsetDateTime,
+) -> Result<()> {
+    info!(
+        "a {} clock at {} and description {} fire!",
+        task.clock_type, at, task.description
+    );
+    let description = match &task.clock_type {
+        ClockType::Pomodoro(pomodoro) => format!("{}: {}", task.description, pomodoro.announce(at)),
+        ClockType::Timer(timer) => format!("{}: {} is up", task.description, timer),
+        ClockType::Once(next_fire) if at < *next_fire => {
+            format!("{} in {}", task.description, lead_time(*next_fire - at))
+        }
+        _ => task.description.clone(),
+    };
+    let body = if missed {
+        let at = in_timezone(at, task.get_timezone());
+        let now = in_timezone(now, task.get_timezone());
+        let at = if at.date() == now.date() {
+            at.format(format_description!("[hour]:[minute]"))
+        } else {
+            at.format(format_description!("[month]-[day] [hour]:[minute]"))
+        }
+        .expect("fail to format the missed fire time");
+        format!("{} (missed at {})", description, at)
+    } else {
+        description
+    };
+    // a pre-alert is sent the plain way, only the fire itself escalates
+    let step = escalation_step(task, now, now).filter(|_| pre_alert_of(task, at).is_none());
+    dispatcher.dispatch(task, &body, step, now)
+}
+
+// the first fire of a task after now and how the following ones are computed; the jitter of a
+// task is seeded by its id unless jitter_seed is given
+fn timing(
+    task: &Task,
+    now: OffsetDateTime,
+    jitter_seed: Option<u64>,
+) -> (Option<OffsetDateTime>, NextFire) {
+    let timezone = task.get_timezone();
+    // wall-clock rules are evaluated in the task's time zone and localized afterwards, so that
+    // the fires follow DST changes
+    let wall_clock = |rule: NextFire| {
+        let next_after: NextFire = Box::new(move |after| {
+            rule(in_timezone(after, timezone)).map(|next| localize(next, timezone))
+        });
+        (next_after(now), next_after)
+    };
+    let (first_fire, next_after): (Option<OffsetDateTime>, NextFire) = match task.clock_type.clone()
+    {
+        ClockType::Once(next_fire) if !task.options.warn.is_empty() => {
+            let fires = with_pre_alerts(next_fire, &task.options.warn);
+            // the passed pre-alerts are dropped, but a passed fire is still caught up
+            let first_fire = fires.iter().find(|fire| **fire > now).unwrap_or(&next_fire);
+            (
+                Some(*first_fire),
+                Box::new(move |after| fires.iter().find(|fire| **fire > after).copied()),
+            )
+        }
+        ClockType::Once(next_fire) => (Some(next_fire), Box::new(|_| None)),
+        ClockType::Period(period) => {
+            let period = parse_duration(&period)
+                .expect("this shall have been verified by the client side")
+                .max(Duration::from_secs(1));
+            match task.options.active_hours.clone() {
+                Some(window) => {
+                    wall_clock(Box::new(move |now| next_in_window(now, period, &window)))
+                }
+                // the fires keep to their start rather than follow the last one, so that neither
+                // late wakes nor jitter offsets add up, and restarts keep the phase
+                None => {
+                    let next_after = every_period_from(task.period_start(period), period);
+                    (next_after(now), next_after)
+                }
+            }
+        }
+        ClockType::Cron(expression) => {
+            let schedule =
+                parse_cron(&expression).expect("this shall have been verified by the client side");
+            wall_clock(Box::new(move |now| schedule.next_after(now)))
         }
+        ClockType::OncePerDay(hour, minute, weekdays) => wall_clock(Box::new(move |now| {
+            next_daily(hour, minute, &weekdays, now)
+        })),
+        ClockType::Monthly(month_day, hour, minute) => wall_clock(Box::new(move |now| {
+            next_monthly(&month_day, hour, minute, now)
+        })),
+        ClockType::Yearly(month, day, hour, minute) => wall_clock(Box::new(move |now| {
+            next_yearly(month, day, hour, minute, now)
+        })),
+        ClockType::RRule(rule, dtstart) => {
+            let rule =
+                parse_rrule(&rule).expect("this shall have been verified by the client side");
+            // the occurrences are localized before they are compared with now and UNTIL
+            let timezone = timezone.or(get_local_timezone());
+            let next_after: NextFire =
+                Box::new(move |after| rule.next_after(dtstart, after, timezone));
+            (next_after(now), next_after)
+        }
+        ClockType::Pomodoro(pomodoro) => {
+            let next_after: NextFire = Box::new(move |after| pomodoro.next_change_after(after));
+            (next_after(now), next_after)
+        }
+        // a paused timer isn't scheduled
+        ClockType::Timer(timer) => (Some(timer.end), Box::new(|_| None)),
+    };
+    let (first_fire, next_after) = match &task.options.jitter {
+        Some(jitter) => {
+            let jitter = parse_duration(jitter)
+                .expect("this shall have been verified by the client side")
+                .try_into()
+                .expect("this shall have been verified by the client side");
+            let seed = jitter_seed.unwrap_or_else(|| seed_of(&task.task_id));
+            let next_after = jittered(next_after, jitter, seed);
+            (next_after(now), next_after)
+        }
+        None => (first_fire, next_after),
+    };
+    let (first_fire, next_after) = match &task.options.skip_calendar {
+        Some(calendar) => skip_calendar(calendar.into(), timezone, first_fire, next_after),
+        None => (first_fire, next_after),
+    };
+    match task.options.until {
+        Some(until) => (
+            first_fire.filter(|first_fire| *first_fire <= until),
+            Box::new(move |after| next_after(after).filter(|next| *next <= until)),
+        ),
+        None => (first_fire, next_after),
+    }
+}
+
+// the first fire of a task strictly after now as the daemon schedules it, leaving aside the fires
+// it has missed
+pub(crate) fn next_fire_after(task: &Task, now: OffsetDateTime) -> Option<OffsetDateTime> {
+    if task.remaining_fires() == Some(0) {
+        return None;
+    }
+    timing(task, now, None).0.filter(|fire| *fire > now)
+}
+
+// the moments of the pre-alerts given by --warn in order, followed by the fire itself; a lead time
+// which doesn't fit before the fire is dropped
+fn with_pre_alerts(fire: OffsetDateTime, warn: &[String]) -> Vec<OffsetDateTime> {
+    let mut fires: Vec<OffsetDateTime> = warn
+        .iter()
+        .filter_map(|lead| {
+            let pre_alert = parse_duration(lead)
+                .ok()
+                .and_then(|lead| time::Duration::try_from(lead).ok())
+                .and_then(|lead| fire.checked_sub(lead));
+            if pre_alert.is_none() {
+                error!("drop the lead time {} of a fire at {}", lead, fire);
+            }
+            pre_alert
+        })
+        .chain([fire])
+        .collect();
+    fires.sort();
+    fires.dedup();
+    fires
+}
+
+// the fire of a one-shot task a fire at the given moment warns of, none if it's the fire itself
+fn pre_alert_of(task: &Task, at: OffsetDateTime) -> Option<OffsetDateTime> {
+    match task.clock_type {
+        ClockType::Once(fire) if at < fire => Some(fire),
+        _ => None,
+    }
+}
+
+// e.g. "1 hour 30 minutes" for a pre-alert
+fn lead_time(lead: time::Duration) -> String {
+    let minutes = (lead.whole_seconds() + 59) / 60; // the pre-alert may fire a bit late
+    let units = [
+        (minutes / (24 * 60), "day"),
+        (minutes / 60 % 24, "hour"),
+        (minutes % 60, "minute"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| match amount {
+            1 => format!("1 {unit}"),
+            _ => format!("{amount} {unit}s"),
+        })
+        .collect();
+    match parts.is_empty() {
+        true => "less than a minute".to_owned(),
+        false => parts.join(" "),
     }
 }
+
+// the occurrences at start and every period after it
+fn every_period_from(start: OffsetDateTime, period: Duration) -> NextFire {
+    let period = time::Duration::try_from(period).expect("a period fits in time::Duration");
+    Box::new(move |after| {
+        if after < start {
+            return Some(start);
+        }
+        let periods = (after - start).whole_nanoseconds() / period.whole_nanoseconds();
+        Some(start + period * (periods + 1) as i32)
+    })
+}
+
+// fires at a pseudo-random offset below jitter after every occurrence of a rule; the offset only
+// depends on the seed and the occurrence, so that the rule can be followed from any moment
+fn jittered(next_after: NextFire, jitter: time::Duration, seed: u64) -> NextFire {
+    Box::new(move |after| {
+        // the occurrences before after - jitter fire before after anyway
+        let mut next = next_after(after - jitter)?;
+        for _ in 0..MAX_CATCH_UP_STEPS {
+            let fire = next + jitter_offset(seed, next, jitter);
+            if fire > after {
+                return Some(fire);
+            }
+            next = next_after(next)?;
+        }
+        None
+    })
+}
+
+// the offset of an occurrence in whole seconds below jitter
+pub fn jitter_offset(
+    seed: u64,
+    occurrence: OffsetDateTime,
+    jitter: time::Duration,
+) -> time::Duration {
+    let seconds = jitter.whole_seconds().max(1);
+    let mut rng = StdRng::seed_from_u64(seed ^ occurrence.unix_timestamp() as u64);
+    time::Duration::seconds(rng.gen_range(0..seconds))
+}
+
+// FNV-1a, which unlike the std hashers stays the same across builds
+fn seed_of(task_id: &str) -> u64 {
+    task_id.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// skips the fires on the dates of an exclusion calendar, in the time zone of the task
+fn skip_calendar(
+    calendar: PathBuf,
+    timezone: Option<&'static Tz>,
+    first_fire: Option<OffsetDateTime>,
+    next_after: NextFire,
+) -> (Option<OffsetDateTime>, NextFire) {
+    // the start of the day after an excluded fire
+    let excluded = move |fire: OffsetDateTime| {
+        let fire = in_timezone(fire, timezone);
+        is_excluded(&calendar, fire.date()).then(|| {
+            localize(
+                fire.replace_time(Time::MIDNIGHT) + time::Duration::DAY,
+                timezone,
+            )
+        })
+    };
+    // jumps over the rest of an excluded day at once
+    let skip = move |mut next: Option<OffsetDateTime>, next_after: &NextFire| {
+        for _ in 0..MAX_SKIPPED_DAYS {
+            match next.and_then(&excluded) {
+                Some(next_day) => next = next_after(next_day - time::Duration::SECOND),
+                None => return next,
+            }
+        }
+        None
+    };
+    let first_fire = skip(first_fire, &next_after);
+    (
+        first_fire,
+        Box::new(move |after| skip(next_after(after), &next_after)),
+    )
+}
+
+fn skips_any(task: &Task, calendars: &[PathBuf]) -> bool {
+    task.options.skip_calendar.as_ref().is_some_and(|calendar| {
+        calendars
+            .iter()
+            .any(|changed| changed == Path::new(calendar))
+    })
+}
+
+// how far the wall clock moved apart from the monotonic one between two wakes
+fn clock_jump(
+    (last_instant, last_moment): (Instant, OffsetDateTime),
+    (instant, moment): (Instant, OffsetDateTime),
+) -> Option<time::Duration> {
+    let monotonic = time::Duration::try_from(instant - last_instant).ok()?;
+    let jump = (moment - last_moment) - monotonic;
+    (jump.abs() > time::Duration::seconds(CLOCK_JUMP_SECS)).then_some(jump)
+}
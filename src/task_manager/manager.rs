@@ -2,23 +2,31 @@ use std::fs::OpenOptions;
 use std::io::{self, BufRead, Write};
 use std::iter::Iterator;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use anyhow::{anyhow, Context, Result};
+use log::error;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use serde_json::from_str;
 use time::OffsetDateTime;
 
 use super::task_context::default_context;
-use super::{ClockType, TaskID};
-use crate::scheduler::Scheduler;
+use super::{ClockType, TaskID, TaskStatus};
+use crate::clock::Clock;
+use crate::comm::{get_local_now_on, get_local_timezone};
+use crate::dispatch::Dnd;
+use crate::rrule::parse_rrule;
+use crate::scheduler::{FireHook, Scheduler};
 use crate::task_manager::task_context::TaskContext;
 use crate::task_manager::Task;
 
 pub struct TaskManager {
     scheduler: Scheduler,
-    tasks: SimpleStore<Task>,
+    tasks: Arc<Mutex<SimpleStore<Task>>>, // shared with the scheduler to persist fire counts
     contexts: SimpleStore<TaskContext>,
+    clock: Arc<dyn Clock>, // the one of the scheduler
 }
 
 impl TaskManager {
@@ -26,7 +34,7 @@ impl TaskManager {
         // push the task to the scheduler
         // and returns back a unique id
         // which would be later used to cancel a periodic task
-        self.tasks.push(task.clone());
+        self.tasks.lock().unwrap().push(task.clone());
         self.scheduler.add_task(task)?;
         Ok(())
     }
@@ -34,19 +42,125 @@ impl TaskManager {
     pub fn get_tasks(&self) -> Vec<Task> {
         let current_context = self.current_context();
         self.tasks
+            .lock()
+            .unwrap()
             .inner()
             .into_iter()
             .filter(|t| t.context == current_context)
             .collect()
     }
 
+    // schedules a one-shot copy of a recently fired task after duration
+    pub fn snooze_task(&mut self, task_id: TaskID, duration: Duration) -> Result<()> {
+        let task = self
+            .scheduler
+            .recently_fired(&task_id)
+            .ok_or_else(|| anyhow!("no task fired recently with id: {task_id}"))?;
+        let next_fire = time::Duration::try_from(duration)
+            .ok()
+            .and_then(|duration| self.local_now().checked_add(duration))
+            .ok_or_else(|| anyhow!("fail to snooze for {:?}: too far away", duration))?;
+        self.add_task(task.snoozed(next_fire))
+    }
+
+    pub fn ack_task(&mut self, task_id: TaskID) -> Result<()> {
+        let task = self
+            .scheduler
+            .recently_fired(&task_id)
+            .filter(|task| task.needs_ack())
+            .ok_or_else(|| anyhow!("no task waiting for acknowledgement with id: {task_id}"))?;
+        let task_id = task.task_id.clone();
+        match self.scheduler.ack_task(task)? {
+            true => Ok(()),
+            false => Err(anyhow!("task {task_id} isn't notified again anymore")),
+        }
+    }
+
+    // the next fires of the tasks in the current context in chronological order, at most limit of
+    // them and only those within window from now
+    pub fn upcoming(
+        &self,
+        limit: usize,
+        window: Option<Duration>,
+    ) -> Result<Vec<(OffsetDateTime, Task)>> {
+        let next_fires = self.scheduler.next_fires()?;
+        // a window too long to end at any moment is no window at all
+        let end = window
+            .and_then(|window| time::Duration::try_from(window).ok())
+            .and_then(|window| self.clock.now_utc().checked_add(window));
+        let mut upcoming: Vec<(OffsetDateTime, Task)> = self
+            .get_tasks()
+            .into_iter()
+            .filter_map(|task| Some((*next_fires.get(&task.task_id)?, task)))
+            .filter(|(at, _)| end.is_none_or(|end| *at <= end))
+            .collect();
+        upcoming.sort_by_key(|(at, _)| *at);
+        upcoming.truncate(limit);
+        Ok(upcoming)
+    }
+
+    pub fn local_now(&self) -> OffsetDateTime {
+        get_local_now_on(&*self.clock)
+    }
+
+    // switches the ad-hoc do-not-disturb and returns the one in effect
+    pub fn set_dnd(&mut self, dnd: Dnd) -> Dnd {
+        self.scheduler.set_dnd(dnd)
+    }
+
+    pub fn dnd_status(&self) -> Dnd {
+        self.scheduler.dnd_status()
+    }
+
     pub fn cancel_task(&mut self, task_id: TaskID) -> Result<()> {
         self.cancel_tasks(vec![task_id])
     }
 
+    // keeps the task in the store but takes it off the scheduler
+    pub fn pause_task(&mut self, task_id: TaskID) -> Result<()> {
+        let task = self.set_status(&task_id, TaskStatus::Paused)?;
+        self.scheduler.cancel_task(task)
+    }
+
+    pub fn resume_task(&mut self, task_id: TaskID) -> Result<()> {
+        let task = self.set_status(&task_id, TaskStatus::Active)?;
+        self.scheduler.add_task(task)
+    }
+
+    // returns the task whose status has been changed
+    fn set_status(&mut self, task_id: &str, status: TaskStatus) -> Result<Task> {
+        let now = self.clock.now_utc();
+        let current_context = self.current_context();
+        let mut tasks = self.tasks.lock().unwrap();
+        let task = tasks
+            .mem
+            .iter_mut()
+            .find(|t| t.context == current_context && t.task_id.starts_with(task_id))
+            .ok_or_else(|| anyhow!("no such task found: {task_id}"))?;
+        if task.status == status {
+            return Err(anyhow!("task {} is already {}", task.task_id, status));
+        }
+        task.status = status;
+        // the occurrences while it's paused aren't missed
+        task.last_fired = None;
+        // a timer counts down only while it's active
+        if let ClockType::Timer(timer) = &mut task.clock_type {
+            *timer = match status {
+                TaskStatus::Paused => timer.paused(now),
+                TaskStatus::Active => timer.resumed(now),
+            };
+        }
+        Ok(task.clone())
+    }
+
     fn cancel_tasks(&mut self, task_ids: Vec<TaskID>) -> Result<()> {
         for task_id in task_ids {
-            if let Some(task) = self.tasks.remove_first(|t| t.task_id.starts_with(&task_id)) {
+            let task = self
+                .tasks
+                .lock()
+                .unwrap()
+                .remove_first(|t| t.task_id.starts_with(&task_id));
+            if let Some(task) = task {
                 self.scheduler.cancel_task(task)?;
             } else {
                 return Err(anyhow!(format!("no such task found: {task_id}")));
@@ -56,15 +170,35 @@ impl TaskManager {
     }
 
     pub fn refresh_before(&mut self) {
-        let now = OffsetDateTime::now_utc();
-        self.tasks.retain(|task| match task.clock_type {
-            ClockType::Once(next_fire) => next_fire > now,
-            _ => true,
+        let now = self.clock.now_utc();
+        self.tasks.lock().unwrap().retain(|task| {
+            // a paused task is kept as is until it's resumed
+            if task.status == TaskStatus::Paused {
+                return true;
+            }
+            if task.is_finished(now) {
+                return false;
+            }
+            match &task.clock_type {
+                ClockType::Once(next_fire) => *next_fire > now,
+                // drop rules which have used up their COUNT or passed their UNTIL
+                ClockType::RRule(rule, dtstart) => parse_rrule(rule)
+                    .map(|rule| {
+                        let timezone = task.get_timezone().or(get_local_timezone());
+                        rule.next_after(*dtstart, now, timezone).is_some()
+                    })
+                    .unwrap_or(true),
+                ClockType::Pomodoro(pomodoro) => pomodoro.end() > now,
+                ClockType::Timer(timer) => timer.end > now,
+                _ => true,
+            }
         });
     }
 
     pub fn refresh_after(&mut self) -> Result<()> {
         self.tasks
+            .lock()
+            .unwrap()
             .refresh_storage()
             .context("fail to refresh task store")?;
         self.contexts
@@ -90,16 +224,28 @@ impl TaskManager {
             contexts.push(default_context());
         }
         let current_context = current_context(&contexts);
-        for task in tasks.iter().filter(|t| t.context == current_context) {
-            scheduler.add_task(task.clone())?;
+        let tasks_to_schedule: Vec<Task> = tasks
+            .iter()
+            .filter(|t| t.context == current_context && t.status == TaskStatus::Active)
+            .cloned()
+            .collect();
+        let tasks = Arc::new(Mutex::new(SimpleStore::new(tasks, task_store_path)));
+        scheduler.set_fire_hook(persist_fire_count(tasks.clone()))?;
+        for task in tasks_to_schedule {
+            scheduler.add_task(task)?;
         }
-        let tasks = SimpleStore::new(tasks, task_store_path);
         let contexts = SimpleStore::new(contexts, context_store_path);
-        let tm = TaskManager {
+        let clock = scheduler.clock();
+        let mut tm = TaskManager {
             scheduler,
             tasks,
             contexts,
+            clock,
         };
+        // the scheduler has caught up the once tasks missed during downtime; drop them now so
+        // that they don't fire again on the next start
+        tm.refresh_before();
+        tm.refresh_after()?;
         Ok(tm)
     }
 
@@ -112,11 +258,22 @@ impl TaskManager {
         if position.is_none() {
             return Err(anyhow!("no such context: {}", &new_context));
         }
-        for task in self.tasks.iter().filter(|t| t.context == current_context) {
+        let tasks = self.tasks.lock().unwrap().inner();
+        let is_active = |t: &&Task| t.status == TaskStatus::Active;
+        for task in tasks
+            .iter()
+            .filter(|t| t.context == current_context)
+            .filter(is_active)
+        {
             self.scheduler.cancel_task(task.to_owned())?;
         }
-        for task in self.tasks.iter().filter(|t| t.context == new_context) {
-            self.scheduler.add_task(task.clone())?;
+        for task in tasks
+            .iter()
+            .filter(|t| t.context == new_context)
+            .filter(is_active)
+            .cloned()
+        {
+            self.scheduler.add_task(task)?;
         }
         let index = position.unwrap();
         self.contexts.swap(0, index);
@@ -142,11 +299,26 @@ impl TaskManager {
             self.switch_context(default_context())?;
         }
         self.contexts.remove_first(|c| c == &context);
-        self.tasks.retain(|t| t.context != context);
+        self.tasks.lock().unwrap().retain(|t| t.context != context);
         Ok(())
     }
 }
 
+// writes the fire count and the last fire of a task back to the store, so that --count survives
+// restarts and the fires missed while the daemon is down are caught up
+fn persist_fire_count(tasks: Arc<Mutex<SimpleStore<Task>>>) -> FireHook {
+    Box::new(move |fired| {
+        let mut tasks = tasks.lock().unwrap();
+        if let Some(task) = tasks.mem.iter_mut().find(|t| t.task_id == fired.task_id) {
+            task.fired = fired.fired;
+            task.last_fired = fired.last_fired;
+            if let Err(e) = tasks.refresh_storage() {
+                error!("fail to persist the fire count of {}: {}", fired.task_id, e);
+            }
+        }
+    })
+}
+
 fn current_context(contexts: &[TaskContext]) -> TaskContext {
     contexts.first().unwrap().clone()
 }
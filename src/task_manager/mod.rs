@@ -2,5 +2,8 @@ pub mod manager;
 mod task;
 mod task_context;
 pub use manager::{read_items, TaskManager};
-pub use task::{ClockType, Task, TaskID};
+pub(crate) use task::ordinal_suffix;
+pub use task::{
+    ActiveHours, ClockType, MissedFire, MonthDay, Task, TaskID, TaskOptions, TaskStatus,
+};
 pub use task_context::TaskContext;
@@ -1,11 +1,21 @@
 use std::fmt::Display;
+use std::time::Duration;
 
+use clap::ValueEnum;
 use nanoid::nanoid;
 use serde::{Deserialize, Serialize};
 use serde_json::to_vec;
-use time::{format_description, OffsetDateTime};
+use time::{format_description, OffsetDateTime, Weekday};
+use time_tz::Tz;
 
 use super::task_context::TaskContext;
+use crate::comm::{parse_timezone, WEEKDAY_NAMES};
+use crate::dispatch::DuringDnd;
+use crate::escalation::Escalation;
+use crate::pomodoro::Pomodoro;
+use crate::rrule::parse_rrule;
+use crate::scheduler;
+use crate::timer::Timer;
 
 pub type TaskID = String;
 
@@ -20,13 +30,157 @@ pub struct Task {
     // media shown when the notification fires
     image_path: Option<String>,
     sound_path: Option<String>,
+
+    #[serde(default)]
+    pub options: TaskOptions,
+    #[serde(default)]
+    pub fired: u32, // how many times it has fired, for options.count
+    // the latest occurrence fired or missed, after which the ones missed while the daemon was down
+    // are caught up on its start
+    #[serde(default)]
+    pub last_fired: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub status: TaskStatus,
+}
+
+// optional settings given by `fmn add`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct TaskOptions {
+    // IANA time zone name for wall-clock based clocks, the local one by default
+    #[serde(default)]
+    pub timezone: Option<String>,
+    // overrides the daemon's policy for missed fires
+    #[serde(default)]
+    pub missed: Option<MissedFire>,
+    // notify again with this interval after a fire until `fmn ack`, e.g. 5m
+    #[serde(default)]
+    pub until_ack: Option<String>,
+    // a recurring task stops after this moment or after this many fires
+    #[serde(default)]
+    pub until: Option<OffsetDateTime>,
+    #[serde(default)]
+    pub count: Option<u32>,
+    // a periodic task only fires inside this window
+    #[serde(default)]
+    pub active_hours: Option<ActiveHours>,
+    #[serde(default)]
+    pub during_dnd: DuringDnd,
+    // a one-shot task also fires this long before, e.g. ["15m", "5m"]
+    #[serde(default)]
+    pub warn: Vec<String>,
+    // a recurring task fires at a random offset up to this after each occurrence, e.g. 10m
+    #[serde(default)]
+    pub jitter: Option<String>,
+    // the absolute path of an .ics file whose dates a recurring task skips
+    #[serde(default)]
+    pub skip_calendar: Option<String>,
+    // a periodic task fires at this moment and every period after it
+    #[serde(default)]
+    pub start: Option<OffsetDateTime>,
+    // notify a fire more and more urgently until `fmn ack`, instead of the same way
+    #[serde(default)]
+    pub escalation: Option<Escalation>,
+}
+
+// the part of the day a periodic task may fire in, e.g. 09:00-18:00 on weekdays
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ActiveHours {
+    pub start: (u8, u8), // hour, minute
+    pub end: (u8, u8),
+    #[serde(default)]
+    pub weekdays: Vec<Weekday>, // empty for everyday
+}
+
+impl Display for ActiveHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start.0, self.start.1, self.end.0, self.end.1
+        )?;
+        if !self.weekdays.is_empty() {
+            let weekdays: Vec<&str> = self
+                .weekdays
+                .iter()
+                .map(|weekday| WEEKDAY_NAMES[weekday.number_days_from_sunday() as usize])
+                .collect();
+            write!(f, " on {}", weekdays.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+// what to do with fires missed while the host slept or the daemon was down
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum MissedFire {
+    Late, // fire every missed occurrence, noting when it was due
+    #[default]
+    Latest, // fire only the latest missed occurrence
+    Skip,
+}
+
+// a paused task stays in the store but is not scheduled until `fmn resume`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskStatus {
+    #[default]
+    Active,
+    Paused,
+}
+
+impl Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskStatus::Active => write!(f, "active"),
+            TaskStatus::Paused => write!(f, "paused"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ClockType {
     Once(OffsetDateTime),
-    OncePerDay(u8, u8), // hour(0-24), minute(0-59)
+    // hour(0-24), minute(0-59), weekdays to fire on (empty for everyday)
+    OncePerDay(u8, u8, #[serde(default)] Vec<Weekday>),
     Period(String),
+    Cron(String),                  // crontab expression, e.g. "30 9 * * 1-5"
+    Monthly(MonthDay, u8, u8),     // day in the month, hour(0-24), minute(0-59)
+    Yearly(u8, u8, u8, u8),        // month(1-12), day(1-31), hour(0-24), minute(0-59)
+    RRule(String, OffsetDateTime), // RFC 5545 recurrence rule, dtstart
+    Pomodoro(Pomodoro),
+    Timer(Timer),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum MonthDay {
+    Day(u8),                 // 1-31; months without that day are skipped
+    NthWeekday(u8, Weekday), // e.g. (2, Tuesday) for the second tuesday; 1-5
+    LastDay,
+}
+
+impl Display for MonthDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MonthDay::Day(day) => write!(f, "{}{}", day, ordinal_suffix(*day as u32)),
+            MonthDay::NthWeekday(nth, weekday) => write!(
+                f,
+                "{}{} {}",
+                nth,
+                ordinal_suffix(*nth as u32),
+                WEEKDAY_NAMES[weekday.number_days_from_sunday() as usize]
+            ),
+            MonthDay::LastDay => write!(f, "last day"),
+        }
+    }
+}
+
+pub(crate) fn ordinal_suffix(n: u32) -> &'static str {
+    match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
 }
 
 impl Display for ClockType {
@@ -46,9 +200,32 @@ impl Display for ClockType {
             ClockType::Period(period) => {
                 write!(f, "every {}", &period)
             }
-            ClockType::OncePerDay(hour, minute) => {
-                write!(f, "everyday {}:{}", hour, minute)
+            ClockType::OncePerDay(hour, minute, weekdays) => {
+                if weekdays.is_empty() {
+                    write!(f, "everyday {}:{}", hour, minute)
+                } else {
+                    let weekdays: Vec<&str> = weekdays
+                        .iter()
+                        .map(|weekday| WEEKDAY_NAMES[weekday.number_days_from_sunday() as usize])
+                        .collect();
+                    write!(f, "{}:{} on {}", hour, minute, weekdays.join(","))
+                }
+            }
+            ClockType::Cron(expression) => {
+                write!(f, "cron {}", expression)
+            }
+            ClockType::Monthly(month_day, hour, minute) => {
+                write!(f, "monthly {} {}:{:02}", month_day, hour, minute)
+            }
+            ClockType::Yearly(month, day, hour, minute) => {
+                write!(f, "yearly {:02}-{:02} {}:{:02}", month, day, hour, minute)
             }
+            ClockType::RRule(rule, dtstart) => match parse_rrule(rule) {
+                Ok(parsed) => write!(f, "{}", parsed.summary(*dtstart)),
+                Err(_) => write!(f, "rrule {}", rule),
+            },
+            ClockType::Pomodoro(pomodoro) => write!(f, "{}", pomodoro),
+            ClockType::Timer(timer) => write!(f, "{}", timer),
         }
     }
 }
@@ -63,15 +240,82 @@ impl Task {
             task_id: nanoid!(),
             image_path: None,
             sound_path: None,
+            options: TaskOptions::default(),
+            fired: 0,
+            last_fired: None,
+            status: TaskStatus::Active,
             // task_id: Uuid::new_v4(),
         }
     }
 
+    // the first fire of a periodic task, which sets the phase of the following ones; the tasks
+    // stored without a start fire a period after their creation
+    pub fn period_start(&self, period: Duration) -> OffsetDateTime {
+        self.options
+            .start
+            .unwrap_or_else(|| self.created_at + period)
+    }
+
+    // the first fire strictly after now, in the task's time zone and phase, with its jitter, skipped
+    // dates and end applied
+    pub fn next_fire_after(&self, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        scheduler::next_fire_after(self, now)
+    }
+
+    // a one-shot copy firing at next_fire, keeping the media and the options which apply to it
+    pub fn snoozed(&self, next_fire: OffsetDateTime) -> Self {
+        Task {
+            clock_type: ClockType::Once(next_fire),
+            created_at: OffsetDateTime::now_utc(),
+            task_id: nanoid!(),
+            fired: 0,
+            last_fired: None,
+            options: TaskOptions {
+                until: None,
+                count: None,
+                warn: vec![],
+                jitter: None,
+                skip_calendar: None,
+                ..self.options.clone()
+            },
+            ..self.clone()
+        }
+    }
+
     pub fn with_context(mut self, context: TaskContext) -> Self {
         self.context = context;
         self
     }
 
+    pub fn with_options(mut self, options: TaskOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    // how many more times it may fire, None for no limit
+    pub fn remaining_fires(&self) -> Option<u32> {
+        self.options
+            .count
+            .map(|count| count.saturating_sub(self.fired))
+    }
+
+    // whether it has used up its --count or passed its --until
+    pub fn is_finished(&self, now: OffsetDateTime) -> bool {
+        self.remaining_fires() == Some(0) || self.options.until.is_some_and(|until| until < now)
+    }
+
+    // whether its fires are notified again until `fmn ack`
+    pub fn needs_ack(&self) -> bool {
+        self.options.until_ack.is_some() || self.options.escalation.is_some()
+    }
+
+    pub fn get_timezone(&self) -> Option<&'static Tz> {
+        self.options
+            .timezone
+            .as_deref()
+            .and_then(|timezone| parse_timezone(timezone).ok())
+    }
+
     pub fn add_image(&mut self, image_path: String) {
         self.image_path = Some(image_path);
     }
@@ -0,0 +1,67 @@
+// a countdown fired once at its end, which keeps its remaining time while paused
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Timer {
+    pub duration: String,       // as given by `fmn timer`, e.g. 25m
+    pub end: OffsetDateTime,    // while it's running
+    pub left: Option<Duration>, // the remaining time while it's paused
+}
+
+impl Timer {
+    pub fn new(duration: String, end: OffsetDateTime) -> Self {
+        Timer {
+            duration,
+            end,
+            left: None,
+        }
+    }
+
+    pub fn remaining(&self, now: OffsetDateTime) -> Duration {
+        self.left
+            .unwrap_or_else(|| self.end - now)
+            .max(Duration::ZERO)
+    }
+
+    pub fn paused(&self, now: OffsetDateTime) -> Self {
+        Timer {
+            left: Some(self.remaining(now)),
+            ..self.clone()
+        }
+    }
+
+    pub fn resumed(&self, now: OffsetDateTime) -> Self {
+        Timer {
+            end: now + self.remaining(now),
+            left: None,
+            ..self.clone()
+        }
+    }
+
+    // the time left, e.g. "12m30s left" or "paused, 1h02m05s left"
+    pub fn status(&self, now: OffsetDateTime) -> String {
+        let left = self.remaining(now);
+        let left = match left.whole_hours() {
+            0 => format!("{}m{:02}s", left.whole_minutes(), left.whole_seconds() % 60),
+            hours => format!(
+                "{}h{:02}m{:02}s",
+                hours,
+                left.whole_minutes() % 60,
+                left.whole_seconds() % 60
+            ),
+        };
+        match self.left {
+            Some(_) => format!("paused, {} left", left),
+            None => format!("{} left", left),
+        }
+    }
+}
+
+impl Display for Timer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "timer {}", self.duration)
+    }
+}
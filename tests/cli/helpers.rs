@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::{io, net::TcpListener, sync::mpsc::SyncSender};
 
 use anyhow::Result;
@@ -5,10 +6,13 @@ use anyhow::Result;
 use assert_cmd::Command;
 use log::{error, info};
 use predicates::str::diff;
+use task_reminder::client::send_request;
+use task_reminder::comm::{Request, Response};
 use task_reminder::format::tabular_output;
-use task_reminder::task_manager::{read_items, Task, TaskContext};
+use task_reminder::task_manager::{read_items, Task, TaskContext, TaskID};
 use task_reminder::{daemon::serve, scheduler::Scheduler, task_manager::TaskManager};
 use tempfile::{tempdir, TempDir};
+use time::OffsetDateTime;
 
 const BINARY_NAME: &str = "fmn";
 
@@ -103,25 +107,67 @@ pub fn rm_task(id: &str) {
 }
 
 pub fn list_tasks(tasks: &Vec<Task>) {
-    let expected_output = format!("{}\n", tabular_output(tasks));
+    let expected_output = format!("{}\n", tabular_output(tasks, &next_fires()));
     fmn(&["list"]).assert().stdout(diff(expected_output));
 }
 
+// sends a request to the daemon directly, e.g. one fmn would reject
+pub fn request(request: Request) -> Response {
+    let dest = std::env::var("FMN_DAEMON_ADDR").expect("no daemon spawned");
+    send_request(request, &dest).expect("fail to send request")
+}
+
+// the next fires of the scheduled tasks, as the daemon tells them
+pub fn next_fires() -> HashMap<TaskID, OffsetDateTime> {
+    let upcoming = Request::Upcoming {
+        limit: usize::MAX,
+        window: None,
+    };
+    match request(upcoming) {
+        Response::Upcoming(upcoming) => upcoming
+            .into_iter()
+            .map(|(at, task)| (task.task_id, at))
+            .collect(),
+        response => panic!("unexpected response: {:?}", response),
+    }
+}
+
 enum AddCommand {
-    After { duration: String },
-    At { time: String, per_day: bool },
-    Per { duration: String },
+    After {
+        duration: String,
+    },
+    At {
+        time: String,
+        per_day: bool,
+        on: Option<String>,
+    },
+    Per {
+        duration: String,
+    },
+    Cron {
+        expression: String,
+    },
+    Monthly {
+        day: String,
+    },
 }
 
 pub struct TestTask<'a> {
     pub description: Option<&'a str>,
     clock_type: AddCommand,
+    options: Vec<&'a str>, // args after the clock type, e.g. --tz Europe/Berlin
 }
 
 const DEFAULT_TASK_NAME: &str = "foo";
 
 impl<'a> TestTask<'a> {
     pub fn to_args(&self) -> Vec<&str> {
+        let mut args = self.clock_type_args();
+        args.extend(self.options.iter());
+        args
+    }
+
+    fn clock_type_args(&self) -> Vec<&str> {
         match &self.clock_type {
             AddCommand::After { duration } => {
                 vec![
@@ -131,23 +177,20 @@ impl<'a> TestTask<'a> {
                     duration,
                 ]
             }
-            AddCommand::At { time, per_day } => {
+            AddCommand::At { time, per_day, on } => {
+                let mut args = vec![
+                    "add",
+                    self.description.unwrap_or(DEFAULT_TASK_NAME),
+                    "at",
+                    time,
+                ];
                 if *per_day {
-                    vec![
-                        "add",
-                        self.description.unwrap_or(DEFAULT_TASK_NAME),
-                        "at",
-                        time,
-                        "-p",
-                    ]
-                } else {
-                    vec![
-                        "add",
-                        self.description.unwrap_or(DEFAULT_TASK_NAME),
-                        "at",
-                        time,
-                    ]
+                    args.push("-p");
+                }
+                if let Some(on) = on {
+                    args.extend(["--on", on]);
                 }
+                args
             }
             AddCommand::Per { duration } => {
                 vec![
@@ -157,6 +200,22 @@ impl<'a> TestTask<'a> {
                     duration,
                 ]
             }
+            AddCommand::Cron { expression } => {
+                vec![
+                    "add",
+                    self.description.unwrap_or(DEFAULT_TASK_NAME),
+                    "cron",
+                    expression,
+                ]
+            }
+            AddCommand::Monthly { day } => {
+                vec![
+                    "add",
+                    self.description.unwrap_or(DEFAULT_TASK_NAME),
+                    "monthly",
+                    day,
+                ]
+            }
         }
     }
 
@@ -166,6 +225,7 @@ impl<'a> TestTask<'a> {
             clock_type: AddCommand::After {
                 duration: "1h".to_owned(),
             },
+            options: vec![],
         }
     }
 
@@ -175,7 +235,20 @@ impl<'a> TestTask<'a> {
     }
 
     pub fn at(mut self, time: String, per_day: bool) -> Self {
-        self.clock_type = AddCommand::At { time, per_day };
+        self.clock_type = AddCommand::At {
+            time,
+            per_day,
+            on: None,
+        };
+        self
+    }
+
+    pub fn at_on(mut self, time: String, weekdays: String) -> Self {
+        self.clock_type = AddCommand::At {
+            time,
+            per_day: false,
+            on: Some(weekdays),
+        };
         self
     }
 
@@ -184,6 +257,76 @@ impl<'a> TestTask<'a> {
         self
     }
 
+    pub fn cron(mut self, expression: String) -> Self {
+        self.clock_type = AddCommand::Cron { expression };
+        self
+    }
+
+    pub fn monthly(mut self, day: String) -> Self {
+        self.clock_type = AddCommand::Monthly { day };
+        self
+    }
+
+    pub fn tz(mut self, timezone: &'a str) -> Self {
+        self.options.extend(["--tz", timezone]);
+        self
+    }
+
+    pub fn missed(mut self, policy: &'a str) -> Self {
+        self.options.extend(["--missed", policy]);
+        self
+    }
+
+    pub fn until_ack(mut self, interval: Option<&'a str>) -> Self {
+        self.options.push("--until-ack");
+        self.options.extend(interval);
+        self
+    }
+
+    pub fn escalate(mut self, end: Option<&'a str>) -> Self {
+        self.options.push("--escalate");
+        self.options.extend(end);
+        self
+    }
+
+    pub fn until(mut self, until: &'a str) -> Self {
+        self.options.extend(["--until", until]);
+        self
+    }
+
+    pub fn count(mut self, count: &'a str) -> Self {
+        self.options.extend(["--count", count]);
+        self
+    }
+
+    pub fn between(mut self, between: &'a str, weekdays: bool) -> Self {
+        self.options.extend(["--between", between]);
+        if weekdays {
+            self.options.push("--weekdays");
+        }
+        self
+    }
+
+    pub fn during_dnd(mut self, during_dnd: &'a str) -> Self {
+        self.options.extend(["--during-dnd", during_dnd]);
+        self
+    }
+
+    pub fn warn(mut self, leads: &'a str) -> Self {
+        self.options.extend(["--warn", leads]);
+        self
+    }
+
+    pub fn jitter(mut self, jitter: &'a str) -> Self {
+        self.options.extend(["--jitter", jitter]);
+        self
+    }
+
+    pub fn skip_calendar(mut self, calendar: &'a str) -> Self {
+        self.options.extend(["--skip-calendar", calendar]);
+        self
+    }
+
     pub fn description(mut self, name: &'a str) -> Self {
         self.description = Some(name);
         self
@@ -1,8 +1,22 @@
 use crate::cli::helpers::rm_task;
 
-use super::helpers::{add_task, list_tasks, spawn_test_daemon, TestTask};
+use super::helpers::{add_task, fmn, list_tasks, next_fires, spawn_test_daemon, TestTask};
 use anyhow::Result;
+use predicates::str::contains;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
 use std::thread::sleep;
+use task_reminder::clock::{Clock, FakeClock};
+use task_reminder::dispatch::{Dnd, DuringDnd};
+use task_reminder::escalation::Escalation;
+use task_reminder::scheduler::{jitter_offset, Scheduler, SchedulerConfig};
+use task_reminder::task_manager::{
+    ClockType, MissedFire, Task, TaskManager, TaskOptions, TaskStatus,
+};
+use tempfile::tempdir;
+use time::macros::datetime;
+use time::{Duration, OffsetDateTime};
 
 #[test]
 fn once_clock() -> Result<()> {
@@ -42,3 +56,585 @@ fn cancel_periodic_clock() -> Result<()> {
     list_tasks(&vec![]);
     Ok(())
 }
+
+#[test]
+fn clocks_share_one_queue() -> Result<()> {
+    let guard = spawn_test_daemon("clocks_share_one_queue")?;
+    add_task(&TestTask::new().description("late").after("3s".to_owned()));
+    add_task(&TestTask::new().description("early").after("1s".to_owned()));
+    add_task(&TestTask::new().description("periodic").per("1s".to_owned()));
+    sleep(std::time::Duration::from_secs(2));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 3);
+    // only the early once task has fired
+    list_tasks(&vec![tasks[0].clone(), tasks[2].clone()]);
+    // cancel the late one before it fires; the queue keeps serving the others
+    rm_task(&tasks[0].task_id);
+    sleep(std::time::Duration::from_secs(2));
+    list_tasks(&vec![tasks[2].clone()]);
+    Ok(())
+}
+
+#[test]
+fn snooze_fired_clock() -> Result<()> {
+    let guard = spawn_test_daemon("snooze_fired_clock")?;
+    let task = TestTask::new().description("tea").after("1s".to_owned());
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    let fired = tasks[0].clone();
+    sleep(std::time::Duration::from_secs(2));
+    // the once task has left the store but could still be snoozed
+    list_tasks(&vec![]);
+    fmn(&["snooze", &fired.task_id[..4], "999999999d"])
+        .assert()
+        .stdout(contains("too far away"));
+    fmn(&["snooze", &fired.task_id[..4], "1h"])
+        .assert()
+        .success();
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].description, fired.description);
+    assert_ne!(tasks[0].task_id, fired.task_id);
+    assert!(matches!(tasks[0].clock_type, ClockType::Once(_)));
+    // a task which hasn't fired can't be snoozed
+    fmn(&["snooze", &tasks[0].task_id, "1h"])
+        .assert()
+        .stdout(contains("no task fired recently"));
+    Ok(())
+}
+
+#[test]
+fn snooze_jittered_clock() -> Result<()> {
+    let guard = spawn_test_daemon("snooze_jittered_clock")?;
+    // keep the clock going despite failing notifications
+    fmn(&["dnd", "on"]).assert().success();
+    add_task(&TestTask::new().per("2s".to_owned()).jitter("1s"));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    let fired = tasks[0].clone();
+    sleep(std::time::Duration::from_millis(2500));
+    fmn(&["snooze", &fired.task_id, "1h"]).assert().success();
+    // the snoozed copy fires exactly then
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 2);
+    let ClockType::Once(snoozed_to) = tasks[1].clock_type else {
+        panic!("not snoozed: {:?}", tasks[1].clock_type);
+    };
+    assert_eq!(tasks[1].options.jitter, None);
+    assert_eq!(next_fires()[&tasks[1].task_id], snoozed_to);
+    Ok(())
+}
+
+#[test]
+fn ack_fired_clock() -> Result<()> {
+    let guard = spawn_test_daemon("ack_fired_clock")?;
+    // keep the clock going despite failing notifications
+    fmn(&["dnd", "on"]).assert().success();
+    let task = TestTask::new()
+        .description("pills")
+        .after("1s".to_owned())
+        .until_ack(None);
+    add_task(&task);
+    add_task(&TestTask::new().description("tea").after("1s".to_owned()));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].options.until_ack.as_deref(), Some("5m"));
+    sleep(std::time::Duration::from_secs(2));
+    fmn(&["ack", &tasks[0].task_id])
+        .assert()
+        .stdout(contains("AckSuccess"));
+    // which stops notifying it again once and for all
+    fmn(&["ack", &tasks[0].task_id])
+        .assert()
+        .stdout(contains("isn't notified again anymore"));
+    // only tasks added with --until-ack wait for acknowledgement
+    fmn(&["ack", &tasks[1].task_id])
+        .assert()
+        .stdout(contains("no task waiting for acknowledgement"));
+    fmn(&["add", "foo", "after", "1h", "--until-ack", "999999999d"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn periodic_clock_with_count() -> Result<()> {
+    let guard = spawn_test_daemon("periodic_clock_with_count")?;
+    let task = TestTask::new().per("1s".to_owned()).count("1");
+    add_task(&task);
+    sleep(std::time::Duration::from_secs(2));
+    // the fire count is persisted without any request in between
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].fired, 1);
+    assert_eq!(tasks[0].options.count, Some(1));
+    // and the task is gone once it has used up its count
+    list_tasks(&vec![]);
+    Ok(())
+}
+
+#[test]
+fn clock_during_dnd() -> Result<()> {
+    let guard = spawn_test_daemon("clock_during_dnd")?;
+    fmn(&["dnd", "on", "1h"])
+        .assert()
+        .stdout(contains("do-not-disturb: on until"));
+    fmn(&["dnd", "status"])
+        .assert()
+        .stdout(contains("do-not-disturb: on until"));
+    let task = TestTask::new().per("1s".to_owned()).during_dnd("drop");
+    add_task(&task);
+    sleep(std::time::Duration::from_secs(2));
+    // the fires are held back rather than notified, and the clock goes on
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert!(tasks[0].fired > 0);
+    assert_eq!(tasks[0].options.during_dnd, DuringDnd::Drop);
+    fmn(&["dnd", "off"])
+        .assert()
+        .stdout(contains("do-not-disturb: off"));
+    fmn(&["dnd", "on", "0s"]).assert().failure();
+    Ok(())
+}
+
+#[test]
+fn pause_and_resume_clock() -> Result<()> {
+    let guard = spawn_test_daemon("pause_and_resume_clock")?;
+    let task = TestTask::new().per("1h".to_owned());
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    let task_id = &tasks[0].task_id;
+    fmn(&["pause", task_id])
+        .assert()
+        .stdout(contains("PauseSuccess"));
+    // a paused task is kept in the store with its configuration
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].status, TaskStatus::Paused);
+    fmn(&["list"]).assert().stdout(contains("paused"));
+    fmn(&["pause", task_id])
+        .assert()
+        .stdout(contains("already paused"));
+    fmn(&["resume", task_id])
+        .assert()
+        .stdout(contains("ResumeSuccess"));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks[0].status, TaskStatus::Active);
+    Ok(())
+}
+
+#[test]
+fn jittered_periodic_clock() -> Result<()> {
+    let guard = spawn_test_daemon("jittered_periodic_clock")?;
+    add_task(&TestTask::new().per("2s".to_owned()).jitter("1s"));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].options.jitter.as_deref(), Some("1s"));
+    for args in [
+        vec!["add", "foo", "after", "1h", "--jitter", "10m"],
+        vec!["add", "foo", "per", "5m", "--jitter", "10m"],
+        vec!["add", "foo", "at", "9:00", "-p", "--jitter", "0s"],
+    ] {
+        fmn(&args).assert().failure();
+    }
+
+    let config = SchedulerConfig {
+        jitter_seed: Some(42),
+        ..Default::default()
+    };
+    let clock = FakeClock::new(datetime!(2022-11-01 08:30 UTC));
+    let (mut scheduler, fires) = fake_scheduler_with(&clock, config)?;
+    let task = Task::new("stretch".to_owned(), ClockType::Period("1h".to_owned())).with_options(
+        TaskOptions {
+            start: Some(datetime!(2022-11-01 09:00 UTC)),
+            jitter: Some("10m".to_owned()),
+            ..Default::default()
+        },
+    );
+    let sleeps = clock.sleeps();
+    scheduler.add_task(task)?;
+    clock.settle(sleeps);
+    // every occurrence is delayed by its own offset, which doesn't add up
+    let occurrences = [
+        datetime!(2022-11-01 09:00 UTC),
+        datetime!(2022-11-01 10:00 UTC),
+        datetime!(2022-11-01 11:00 UTC),
+    ];
+    for (fired, occurrence) in occurrences.into_iter().enumerate() {
+        let at = occurrence + jitter_offset(42, occurrence, Duration::minutes(10));
+        clock.advance_to(at - Duration::seconds(1));
+        assert!(fires.try_recv().is_err(), "fired before {}", at);
+        clock.advance_to(at);
+        assert_eq!(fires.try_recv()?, (at, fired as u32 + 1));
+    }
+    Ok(())
+}
+
+#[test]
+fn pomodoro_clock() -> Result<()> {
+    let guard = spawn_test_daemon("pomodoro_clock")?;
+    fmn(&["pomodoro", "status"])
+        .assert()
+        .stdout(contains("no pomodoro running"));
+    fmn(&[
+        "pomodoro", "start", "essay", "--work", "1h", "--rounds", "2",
+    ])
+    .assert()
+    .success();
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert!(matches!(tasks[0].clock_type, ClockType::Pomodoro(_)));
+    fmn(&["pomodoro", "status"])
+        .assert()
+        .stdout(contains("essay: work, round 1/2, 59m"));
+    // a single rm stops the whole chain
+    rm_task(&tasks[0].task_id);
+    fmn(&["pomodoro", "status"])
+        .assert()
+        .stdout(contains("no pomodoro running"));
+    fmn(&["pomodoro", "start", "--break", "0s"])
+        .assert()
+        .failure();
+    fmn(&["pomodoro", "start", "--work", "999999999d"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn once_clock_with_pre_alerts() -> Result<()> {
+    let guard = spawn_test_daemon("once_clock_with_pre_alerts")?;
+    // keep the clock going despite failing notifications
+    fmn(&["dnd", "on"]).assert().success();
+    let task = TestTask::new()
+        .description("meeting")
+        .after("2s".to_owned())
+        .warn("1s");
+    add_task(&task);
+    let cancelled = TestTask::new().after("3s".to_owned()).warn("2s");
+    add_task(&cancelled);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0].options.warn, vec!["1s"]);
+    // the pre-alerts are bound to their task
+    let cancelled_id = tasks[1].task_id.clone();
+    rm_task(&cancelled_id);
+    sleep(std::time::Duration::from_millis(1500));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].fired, 1);
+    fmn(&["snooze", &cancelled_id])
+        .assert()
+        .stdout(contains("no task fired recently"));
+
+    for args in [
+        vec!["add", "foo", "per", "1h", "--warn", "5m"],
+        vec!["add", "foo", "after", "1h", "--warn", "15m,0s"],
+        vec!["add", "foo", "after", "1h", "--warn", "1h"],
+        vec!["add", "foo", "after", "1h", "--warn", "999999999d"],
+    ] {
+        fmn(&args).assert().failure();
+    }
+    Ok(())
+}
+
+// a scheduler on a fake clock, reporting the moment of every fire
+fn fake_scheduler(clock: &FakeClock) -> Result<(Scheduler, Receiver<(OffsetDateTime, u32)>)> {
+    fake_scheduler_with(clock, SchedulerConfig::default())
+}
+
+fn fake_scheduler_with(
+    clock: &FakeClock,
+    config: SchedulerConfig,
+) -> Result<(Scheduler, Receiver<(OffsetDateTime, u32)>)> {
+    let mut scheduler = Scheduler::with_clock(config, Arc::new(clock.clone()));
+    // the notifications can't be sent here, and a task failing to notify is dropped
+    scheduler.set_dnd(Dnd::On);
+    clock.settle(0);
+    let (tx, rx) = channel();
+    let hook_clock = clock.clone();
+    let sleeps = clock.sleeps();
+    scheduler.set_fire_hook(Box::new(move |task: &Task| {
+        tx.send((hook_clock.now_utc(), task.fired)).unwrap();
+    }))?;
+    clock.settle(sleeps);
+    Ok((scheduler, rx))
+}
+
+fn daily_task() -> Task {
+    Task::new("standup".to_owned(), ClockType::OncePerDay(9, 30, vec![])).with_options(
+        TaskOptions {
+            timezone: Some("Europe/Berlin".to_owned()),
+            missed: Some(MissedFire::Latest),
+            ..Default::default()
+        },
+    )
+}
+
+#[test]
+fn fake_clock_through_days_and_dst() -> Result<()> {
+    let clock = FakeClock::new(datetime!(2022-10-28 12:00 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(daily_task())?;
+    clock.settle(sleeps);
+
+    // 09:30 in Berlin is 07:30 UTC in summer time and 08:30 UTC after it ends on 2022-10-30
+    let expected = [
+        datetime!(2022-10-29 07:30 UTC),
+        datetime!(2022-10-30 08:30 UTC),
+        datetime!(2022-10-31 08:30 UTC),
+    ];
+    for (fired, at) in expected.into_iter().enumerate() {
+        clock.advance_to(at - Duration::minutes(1));
+        assert!(fires.try_recv().is_err(), "fired before {}", at);
+        clock.advance_to(at);
+        assert_eq!(fires.try_recv()?, (at, fired as u32 + 1));
+    }
+    clock.advance(std::time::Duration::from_secs(12 * 3600));
+    assert!(fires.try_recv().is_err());
+    Ok(())
+}
+
+#[test]
+fn fake_clock_through_suspend() -> Result<()> {
+    let clock = FakeClock::new(datetime!(2022-11-01 08:00 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(daily_task())?;
+    clock.settle(sleeps);
+
+    // the host sleeps over three fires and wakes up hours after the last one
+    clock.suspend(std::time::Duration::from_secs(3 * 24 * 3600));
+    assert!(fires.try_recv().is_err());
+    clock.advance(std::time::Duration::from_secs(30));
+    let woken = datetime!(2022-11-04 08:00:30 UTC);
+    // only the latest missed fire is notified
+    assert_eq!(fires.try_recv()?, (woken, 1));
+    assert!(fires.try_recv().is_err());
+
+    clock.advance_to(datetime!(2022-11-04 08:30 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-04 08:30 UTC), 2));
+    Ok(())
+}
+
+#[test]
+fn fake_clock_anchored_period() -> Result<()> {
+    let task = Task::new("stretch".to_owned(), ClockType::Period("1h".to_owned())).with_options(
+        TaskOptions {
+            start: Some(datetime!(2022-11-01 09:00 UTC)),
+            ..Default::default()
+        },
+    );
+    let clock = FakeClock::new(datetime!(2022-11-01 09:20:17 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(task.clone())?;
+    clock.settle(sleeps);
+    // fired on the hour, even when woken up late
+    clock.advance_to(datetime!(2022-11-01 10:00:20 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 10:00:20 UTC), 1));
+    clock.advance_to(datetime!(2022-11-01 10:59:59 UTC));
+    assert!(fires.try_recv().is_err());
+    clock.advance_to(datetime!(2022-11-01 11:00 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 11:00 UTC), 2));
+    drop(scheduler);
+
+    // a restarted daemon keeps the phase
+    let clock = FakeClock::new(datetime!(2022-11-01 11:41 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(task)?;
+    clock.settle(sleeps);
+    clock.advance_to(datetime!(2022-11-01 11:59 UTC));
+    assert!(fires.try_recv().is_err());
+    clock.advance_to(datetime!(2022-11-01 12:00 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 12:00 UTC), 1));
+    Ok(())
+}
+
+// a task manager on a fake clock, restarted on the task store in dir
+fn fake_task_manager(clock: &FakeClock, dir: &Path) -> Result<TaskManager> {
+    let scheduler = Scheduler::with_clock(SchedulerConfig::default(), Arc::new(clock.clone()));
+    // the notifications can't be sent here, and a task failing to notify is dropped
+    scheduler.set_dnd(Dnd::On);
+    let tm = TaskManager::new(dir, scheduler)?;
+    // answered once the inner scheduler has handled the tasks of the store
+    tm.upcoming(0, None)?;
+    Ok(tm)
+}
+
+#[test]
+fn fake_clock_missed_while_down() -> Result<()> {
+    let dir = tempdir()?;
+    let clock = FakeClock::new(datetime!(2022-11-01 12:00 UTC));
+    let mut tm = fake_task_manager(&clock, dir.path())?;
+    // they have fired at 09:30 in Berlin today
+    let fired_at = Some(datetime!(2022-11-01 08:30 UTC));
+    for policy in [MissedFire::Late, MissedFire::Latest, MissedFire::Skip] {
+        let mut task = daily_task().with_context(tm.current_context());
+        task.description = format!("{:?}", policy);
+        task.options.missed = Some(policy);
+        task.fired = 1;
+        task.last_fired = fired_at;
+        tm.add_task(task)?;
+    }
+    let fires = |tm: &TaskManager| -> Vec<(String, u32, Option<OffsetDateTime>)> {
+        tm.get_tasks()
+            .into_iter()
+            .map(|task| (task.description, task.fired, task.last_fired))
+            .collect()
+    };
+    tm.upcoming(0, None)?;
+    assert!(fires(&tm).iter().all(|(_, fired, _)| *fired == 1));
+    tm.refresh_after()?;
+    drop(tm);
+
+    // the daemon is down over the next three fires
+    let clock = FakeClock::new(datetime!(2022-11-04 12:00 UTC));
+    let tm = fake_task_manager(&clock, dir.path())?;
+    let latest = Some(datetime!(2022-11-04 08:30 UTC));
+    assert_eq!(
+        fires(&tm),
+        vec![
+            ("Late".to_owned(), 4, latest),
+            ("Latest".to_owned(), 2, latest),
+            ("Skip".to_owned(), 1, latest),
+        ]
+    );
+    drop(tm);
+
+    // nor are they missed again on the next start
+    let clock = FakeClock::new(datetime!(2022-11-04 13:00 UTC));
+    let tm = fake_task_manager(&clock, dir.path())?;
+    assert_eq!(fires(&tm)[0], ("Late".to_owned(), 4, latest));
+    assert_eq!(fires(&tm)[1], ("Latest".to_owned(), 2, latest));
+    Ok(())
+}
+
+#[test]
+fn fake_clock_stale_pre_alerts() -> Result<()> {
+    let task = Task::new(
+        "meeting".to_owned(),
+        ClockType::Once(datetime!(2022-11-01 09:00 UTC)),
+    )
+    .with_options(TaskOptions {
+        missed: Some(MissedFire::Late),
+        warn: vec!["15m".to_owned(), "5m".to_owned()],
+        ..Default::default()
+    });
+    let clock = FakeClock::new(datetime!(2022-11-01 08:00 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(task)?;
+    clock.settle(sleeps);
+    // the host sleeps over the pre-alerts and the meeting, and only the meeting is notified late
+    clock.suspend(std::time::Duration::from_secs(2 * 3600));
+    clock.advance(std::time::Duration::from_secs(30));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 10:00:30 UTC), 1));
+    assert!(fires.try_recv().is_err());
+    Ok(())
+}
+
+#[test]
+fn fake_clock_out_of_range_lead() -> Result<()> {
+    // stored by an older fmn, which didn't bound the lead times
+    let task = Task::new(
+        "meeting".to_owned(),
+        ClockType::Once(datetime!(2022-11-01 09:00 UTC)),
+    )
+    .with_options(TaskOptions {
+        warn: vec!["999999999d".to_owned(), "5m".to_owned()],
+        ..Default::default()
+    });
+    let clock = FakeClock::new(datetime!(2022-11-01 08:00 UTC));
+    let (mut scheduler, fires) = fake_scheduler(&clock)?;
+    let sleeps = clock.sleeps();
+    scheduler.add_task(task)?;
+    clock.settle(sleeps);
+    clock.advance_to(datetime!(2022-11-01 08:55 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 08:55 UTC), 1));
+    clock.advance_to(datetime!(2022-11-01 09:00 UTC));
+    assert_eq!(fires.try_recv()?, (datetime!(2022-11-01 09:00 UTC), 2));
+    Ok(())
+}
+
+#[test]
+fn escalating_clock() -> Result<()> {
+    let guard = spawn_test_daemon("escalating_clock")?;
+    // keep the clock going despite failing notifications
+    fmn(&["dnd", "on"]).assert().success();
+    let task = TestTask::new()
+        .description("pills")
+        .after("1s".to_owned())
+        .escalate(None);
+    add_task(&task);
+    add_task(&TestTask::new().after("1h".to_owned()).escalate(Some("10m")));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(
+        tasks[0].options.escalation,
+        Some(Escalation::profile("30m".to_owned()))
+    );
+    assert_eq!(
+        tasks[1].options.escalation.as_ref().map(|e| e.end.as_str()),
+        Some("10m")
+    );
+    sleep(std::time::Duration::from_secs(2));
+    // an escalation goes on until acknowledged
+    fmn(&["ack", &tasks[0].task_id])
+        .assert()
+        .stdout(contains("AckSuccess"));
+    fmn(&["ack", &tasks[0].task_id])
+        .assert()
+        .stdout(contains("isn't notified again anymore"));
+
+    for args in [
+        vec!["add", "foo", "after", "1h", "--escalate", "0s"],
+        vec!["add", "foo", "after", "1h", "--escalate", "--until-ack"],
+    ] {
+        fmn(&args).assert().failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn timer_clock() -> Result<()> {
+    let guard = spawn_test_daemon("timer_clock")?;
+    // keep the clock going despite failing notifications
+    fmn(&["dnd", "on"]).assert().success();
+    fmn(&["timer", "status"])
+        .assert()
+        .stdout(contains("no timer running"));
+    fmn(&["timer", "2s", "tea"]).assert().success();
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert!(matches!(tasks[0].clock_type, ClockType::Timer(_)));
+    let task_id = tasks[0].task_id.clone();
+    fmn(&["timer", "status"])
+        .assert()
+        .stdout(contains("tea: 0m01s left"));
+
+    // a paused timer keeps its time left
+    fmn(&["timer", "pause", &task_id]).assert().success();
+    sleep(std::time::Duration::from_secs(3));
+    fmn(&["timer", "status"])
+        .assert()
+        .stdout(contains("tea: paused, 0m01s left"));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks[0].fired, 0);
+    fmn(&["timer", "resume", &task_id]).assert().success();
+    fmn(&["timer", "status"])
+        .assert()
+        .stdout(contains("tea: 0m01s left"));
+    sleep(std::time::Duration::from_secs(3));
+    fmn(&["timer", "status"])
+        .assert()
+        .stdout(contains("no timer running"));
+
+    fmn(&["timer", "0s"]).assert().failure();
+    fmn(&["timer"]).assert().failure();
+    Ok(())
+}
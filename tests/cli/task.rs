@@ -1,9 +1,16 @@
+use std::fs;
+
 use anyhow::{anyhow, Result};
-use task_reminder::{comm::get_local_now, task_manager::ClockType};
+use predicates::str::{contains, diff};
+use task_reminder::comm::{get_local_now, DndCommand, Request, Response};
+use task_reminder::format::upcoming_output;
+use task_reminder::task_manager::{ClockType, MissedFire, MonthDay, Task};
+use tempfile::tempdir;
+use time::Weekday;
 
-use crate::cli::helpers::list_tasks;
+use crate::cli::helpers::{list_tasks, next_fires, request};
 
-use super::helpers::{add_task, rm_task, spawn_test_daemon, TestTask};
+use super::helpers::{add_task, fmn, rm_task, spawn_test_daemon, TestTask};
 
 #[test]
 fn remove_task() -> Result<()> {
@@ -68,6 +75,23 @@ fn check_clock_type_at() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn check_clock_type_at_on_weekdays() -> Result<()> {
+    let guard = spawn_test_daemon("check_clock_type")?;
+    let task = TestTask::new()
+        .description("foo")
+        .at_on("9:30".to_owned(), "fri,mon".to_owned());
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(
+        tasks[0].clock_type,
+        ClockType::OncePerDay(9, 30, vec![Weekday::Monday, Weekday::Friday])
+    );
+    list_tasks(&tasks);
+    Ok(())
+}
+
 #[test]
 fn check_clock_type_per() -> Result<()> {
     let guard = spawn_test_daemon("check_clock_type")?;
@@ -86,3 +110,276 @@ fn check_clock_type_per() -> Result<()> {
     }
     Ok(())
 }
+
+#[test]
+fn check_clock_type_cron() -> Result<()> {
+    let guard = spawn_test_daemon("check_clock_type")?;
+    let input_expression = "30 9 * * 1-5";
+    let task = TestTask::new()
+        .description("foo")
+        .cron(input_expression.to_owned());
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    if let ClockType::Cron(expression) = &tasks[0].clock_type {
+        assert_eq!(input_expression, expression);
+    } else {
+        return Err(anyhow!("wrong clock type"));
+    }
+    Ok(())
+}
+
+#[test]
+fn check_clock_type_monthly() -> Result<()> {
+    let guard = spawn_test_daemon("check_clock_type")?;
+    let task = TestTask::new()
+        .description("rent")
+        .monthly("2nd-tue".to_owned());
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(
+        tasks[0].clock_type,
+        ClockType::Monthly(MonthDay::NthWeekday(2, Weekday::Tuesday), 9, 0)
+    );
+    list_tasks(&tasks);
+    Ok(())
+}
+
+#[test]
+fn check_timezone() -> Result<()> {
+    let guard = spawn_test_daemon("check_timezone")?;
+    let task = TestTask::new()
+        .description("foo")
+        .at("9:00".to_owned(), true)
+        .tz("Asia/Kolkata");
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].clock_type, ClockType::OncePerDay(9, 0, vec![]));
+    assert_eq!(tasks[0].options.timezone.as_deref(), Some("Asia/Kolkata"));
+    list_tasks(&tasks);
+    Ok(())
+}
+
+#[test]
+fn check_missed_policy() -> Result<()> {
+    let guard = spawn_test_daemon("check_missed_policy")?;
+    let task = TestTask::new()
+        .description("foo")
+        .per("1h".to_owned())
+        .missed("late");
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].options.missed, Some(MissedFire::Late));
+    fmn(&["add", "foo", "per", "1h", "--missed", "never"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn check_end_conditions() -> Result<()> {
+    let guard = spawn_test_daemon("check_end_conditions")?;
+    let next_year = get_local_now().year() + 1;
+    let until = format!("{next_year}-12-20");
+    let task = TestTask::new()
+        .description("standup")
+        .at("9:30".to_owned(), true)
+        .until(&until)
+        .count("8");
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].options.count, Some(8));
+    let until = tasks[0].options.until.expect("no until");
+    assert_eq!(
+        (until.year(), until.hour(), until.minute()),
+        (next_year, 23, 59)
+    );
+    list_tasks(&tasks);
+
+    for args in [
+        vec!["add", "foo", "after", "1h", "--count", "3"],
+        vec!["add", "foo", "per", "1h", "--count", "0"],
+        vec!["add", "foo", "per", "1h", "--until", "2020-01-01"],
+    ] {
+        fmn(&args).assert().failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn check_active_hours() -> Result<()> {
+    let guard = spawn_test_daemon("check_active_hours")?;
+    let task = TestTask::new()
+        .description("stretch")
+        .per("45m".to_owned())
+        .between("09:00-18:00", true);
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    let active_hours = tasks[0]
+        .options
+        .active_hours
+        .clone()
+        .expect("no active hours");
+    assert_eq!((active_hours.start, active_hours.end), ((9, 0), (18, 0)));
+    assert_eq!(active_hours.weekdays.len(), 5);
+    list_tasks(&tasks);
+
+    for args in [
+        vec!["add", "foo", "at", "9:00", "--between", "09:00-18:00"],
+        vec!["add", "foo", "per", "10h", "--between", "09:00-18:00"],
+        vec!["add", "foo", "per", "1h", "--between", "18:00-09:00"],
+    ] {
+        fmn(&args).assert().failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn check_skip_calendar() -> Result<()> {
+    let guard = spawn_test_daemon("check_skip_calendar")?;
+    let dir = tempdir()?;
+    let calendar = dir.path().join("holidays.ics");
+    fs::write(
+        &calendar,
+        "BEGIN:VCALENDAR\nBEGIN:VEVENT\nDTSTART;VALUE=DATE:20261225\nEND:VEVENT\nEND:VCALENDAR\n",
+    )?;
+    let calendar = calendar.to_string_lossy();
+    let task = TestTask::new()
+        .description("standup")
+        .cron("30 9 * * 1-5".to_owned())
+        .skip_calendar(&calendar);
+    add_task(&task);
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].options.skip_calendar.as_deref(), Some(&*calendar));
+    fmn(&["list"])
+        .assert()
+        .stdout(contains("skip holidays.ics"));
+
+    let missing = dir.path().join("missing.ics");
+    for args in [
+        vec!["add", "foo", "after", "1h", "--skip-calendar", &calendar],
+        vec![
+            "add",
+            "foo",
+            "per",
+            "1h",
+            "--skip-calendar",
+            &missing.to_string_lossy(),
+        ],
+    ] {
+        fmn(&args).assert().failure();
+    }
+    Ok(())
+}
+
+#[test]
+fn check_upcoming() -> Result<()> {
+    let guard = spawn_test_daemon("check_upcoming")?;
+    add_task(&TestTask::new().description("later").after("2h".to_owned()));
+    add_task(&TestTask::new().description("sooner").after("1h".to_owned()));
+    add_task(&TestTask::new().description("periodic").per("3h".to_owned()));
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 3);
+    let fire_of = |task: &Task| match task.clock_type {
+        ClockType::Once(moment) => moment,
+        _ => panic!("not a one-shot task"),
+    };
+
+    // a period counts from when the daemon scheduled it
+    let periodic = next_fires()[&tasks[2].task_id];
+    assert!(periodic > get_local_now() + time::Duration::minutes(179));
+    let expected = vec![
+        (fire_of(&tasks[1]), tasks[1].clone()),
+        (fire_of(&tasks[0]), tasks[0].clone()),
+        (periodic, tasks[2].clone()),
+    ];
+    fmn(&["next"])
+        .assert()
+        .stdout(diff(format!("{}\n", upcoming_output(&expected))));
+    fmn(&["next", "1"])
+        .assert()
+        .stdout(diff(format!("{}\n", upcoming_output(&expected[..1]))));
+    fmn(&["next", "--within", "90m"])
+        .assert()
+        .stdout(diff(format!("{}\n", upcoming_output(&expected[..1]))));
+    fmn(&["next", "--within", "30m"])
+        .assert()
+        .stdout(contains("no upcoming task"));
+    fmn(&["next", "--within", "soon"]).assert().failure();
+
+    // a paused task has no next fire
+    fmn(&["pause", &tasks[2].task_id]).assert().success();
+    assert!(!next_fires().contains_key(&tasks[2].task_id));
+    list_tasks(&guard.read_tasks()?);
+    fmn(&["list"]).assert().stdout(contains(" - "));
+    Ok(())
+}
+
+#[test]
+fn check_period_start() -> Result<()> {
+    let guard = spawn_test_daemon("check_period_start")?;
+    let now = get_local_now();
+    add_task(&TestTask::new().per("1h".to_owned()));
+    // a time alone sets the phase only
+    let minute = (now.minute() + 30) % 60;
+    let start = format!("{}:{:02}", now.hour(), minute);
+    fmn(&["add", "on the half hour", "per", "1h", "--start", &start])
+        .assert()
+        .success();
+    let tasks = guard.read_tasks()?;
+    assert_eq!(tasks.len(), 2);
+    let default_start = tasks[0].options.start.expect("no default start");
+    assert!(default_start - now >= time::Duration::minutes(59));
+    let start = tasks[1].options.start.expect("no start");
+    assert_eq!(start.minute(), minute);
+    assert!(start >= now.replace_second(0)? && start - now <= time::Duration::hours(1));
+    let next_fire = next_fires()[&tasks[1].task_id];
+    assert_eq!((next_fire.minute(), next_fire.second()), (minute, 0));
+
+    fmn(&[
+        "add",
+        "foo",
+        "per",
+        "1h",
+        "--start",
+        "9:00",
+        "--between",
+        "09:00-18:00",
+    ])
+    .assert()
+    .failure();
+    fmn(&["add", "foo", "per", "1h", "--start", "soon"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn reject_malformed_requests() -> Result<()> {
+    let _guard = spawn_test_daemon("reject_malformed_requests")?;
+    // requests fmn would reject fail without taking the daemon down
+    let requests = [
+        Request::Snooze("foo".to_owned(), "soon".to_owned()),
+        Request::Upcoming {
+            limit: 1,
+            window: Some("soon".to_owned()),
+        },
+        Request::Dnd(DndCommand::On {
+            duration: Some("soon".to_owned()),
+        }),
+        Request::Dnd(DndCommand::On {
+            duration: Some("999999999d".to_owned()),
+        }),
+    ];
+    for malformed in requests {
+        assert!(matches!(request(malformed), Response::Fail(_)));
+    }
+    list_tasks(&vec![]);
+    Ok(())
+}
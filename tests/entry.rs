@@ -1,5 +1,9 @@
 mod cli;
 mod fmn;
+mod natural;
+mod pomodoro;
+mod rrule;
+mod timer;
 
 #[cfg(test)]
 #[ctor::ctor]
@@ -1,7 +1,18 @@
 use std::time::Duration;
 
 use anyhow::Result;
-use task_reminder::comm::{parse_at, parse_duration};
+use task_reminder::calendar::parse_ics;
+use task_reminder::comm::{
+    get_local_now, get_local_timezone, localize, next_daily, next_in_window, next_monthly,
+    next_yearly, parse_at, parse_at_in, parse_between, parse_cron, parse_duration,
+    parse_month_and_day, parse_month_day, parse_quiet_hours, parse_timezone, parse_weekdays,
+};
+use task_reminder::dispatch::{effective_dnd, Dnd, QuietHours};
+use task_reminder::escalation::{Escalation, Urgency};
+use task_reminder::scheduler::jitter_offset;
+use task_reminder::task_manager::{ActiveHours, ClockType, MonthDay, Task, TaskOptions};
+use time::macros::datetime;
+use time::{Date, Month, Weekday};
 
 #[test]
 fn test_duration() -> Result<()> {
@@ -36,7 +47,7 @@ fn test_parse_at() -> Result<()> {
     // no support for seconds
     let test_cases = vec![("13:24", 13, 24), ("23:01", 23, 1), ("01:59", 1, 59)];
     for (next_fire, hour, minute) in test_cases {
-        let next_fire = parse_at(next_fire)?;
+        let next_fire = parse_at(next_fire, get_local_now())?;
         let parsed_hour = next_fire.hour();
         let parsed_minute = next_fire.minute();
         assert_eq!(hour, parsed_hour);
@@ -48,8 +59,687 @@ fn test_parse_at() -> Result<()> {
 
 #[test]
 fn test_parse_at_err() {
-    let test_cases = vec!["123:24", "11:94", "098", ""];
+    let test_cases = vec![
+        "123:24",
+        "11:94",
+        "098",
+        "",
+        "2026-13-01 10:00",
+        "02-30 10:00",
+    ];
     for next_fire in test_cases {
-        assert!(parse_at(next_fire).is_err());
+        assert!(parse_at(next_fire, get_local_now()).is_err(), "{next_fire}");
+    }
+}
+
+#[test]
+fn test_parse_at_date() -> Result<()> {
+    let next_year = get_local_now().year() + 1;
+    let next_fire = parse_at(&format!("{next_year}-11-03 14:00"), get_local_now())?;
+    assert_eq!(
+        next_fire.date(),
+        Date::from_calendar_date(next_year, Month::November, 3)?
+    );
+    assert_eq!((next_fire.hour(), next_fire.minute()), (14, 0));
+    // the offset in effect on that date, not the current one
+    assert_eq!(next_fire, localize(next_fire, get_local_timezone()));
+
+    let next_fire = parse_at(
+        &format!("{next_year}-11-03T14:00:30+01:00"),
+        get_local_now(),
+    )?;
+    assert_eq!(
+        next_fire,
+        datetime!(2000-11-03 13:00:30 UTC).replace_year(next_year)?
+    );
+
+    let next_fire = parse_at(&format!("{next_year}-02-28T23:30Z"), get_local_now())?;
+    assert_eq!(
+        next_fire,
+        datetime!(2000-02-28 23:30 UTC).replace_year(next_year)?
+    );
+
+    // without a year it's this year
+    let tomorrow = get_local_now() + time::Duration::days(1);
+    let next_fire = parse_at(
+        &format!("{}-{} 23:59", tomorrow.month() as u8, tomorrow.day()),
+        get_local_now(),
+    )?;
+    assert_eq!(next_fire.date(), tomorrow.date());
+    Ok(())
+}
+
+#[test]
+fn test_parse_at_past_date() {
+    let last_year = get_local_now().year() - 1;
+    let yesterday = get_local_now() - time::Duration::days(1);
+    let mut test_cases = vec![
+        format!("{last_year}-11-03 14:00"),
+        format!("{last_year}-11-03T14:00:00Z"),
+    ];
+    if yesterday.year() == get_local_now().year() {
+        test_cases.push(format!(
+            "{}-{} 00:00",
+            yesterday.month() as u8,
+            yesterday.day()
+        ));
+    }
+    for next_fire in test_cases {
+        let err = parse_at(&next_fire, get_local_now()).unwrap_err();
+        assert!(
+            err.to_string().contains("in the past"),
+            "{next_fire}: {err}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_cron() -> Result<()> {
+    // 2022-11-04 is a friday
+    let now = datetime!(2022-11-04 10:00 +8);
+    let test_cases = vec![
+        ("30 9 * * 1-5", datetime!(2022-11-07 9:30 +8)),
+        ("*/15 * * * *", datetime!(2022-11-04 10:15 +8)),
+        ("0 0 1 * *", datetime!(2022-12-01 0:00 +8)),
+        ("0 12 * * sat,sun", datetime!(2022-11-05 12:00 +8)),
+        ("0 8 29 feb *", datetime!(2024-02-29 8:00 +8)),
+        // either the 13th or a friday
+        ("0 9 13 * 5", datetime!(2022-11-11 9:00 +8)),
+    ];
+    for (expression, expected) in test_cases {
+        let schedule = parse_cron(expression)?;
+        assert_eq!(schedule.next_after(now), Some(expected), "{expression}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_cron_err() {
+    let test_cases = vec![
+        "",
+        "* * * *",
+        "60 * * * *",
+        "* 24 * * *",
+        "* * 0 * *",
+        "* * * 13 *",
+        "* * * * 8",
+        "*/0 * * * *",
+        "5-1 * * * *",
+        "0 9 30 2 *",
+    ];
+    for expression in test_cases {
+        assert!(parse_cron(expression).is_err(), "{expression}");
+    }
+}
+
+#[test]
+fn test_parse_weekdays() -> Result<()> {
+    let test_cases = vec![
+        ("mon", vec![Weekday::Monday]),
+        (
+            "fri,Mon,wednesday",
+            vec![Weekday::Monday, Weekday::Wednesday, Weekday::Friday],
+        ),
+        ("sun,sat,sun", vec![Weekday::Saturday, Weekday::Sunday]),
+    ];
+    for (weekdays, expected) in test_cases {
+        assert_eq!(parse_weekdays(weekdays)?, expected);
+    }
+    for weekdays in [
+        "", "mo", "mon,,fri", "foo", "monkey", "aaé", "éé", "mondays",
+    ] {
+        assert!(parse_weekdays(weekdays).is_err(), "{weekdays}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_once_per_day_without_weekdays() -> Result<()> {
+    // tasks persisted before weekdays were supported
+    let clock_type: ClockType = serde_json::from_str(r#"{"OncePerDay":[9,30]}"#)?;
+    assert_eq!(clock_type, ClockType::OncePerDay(9, 30, vec![]));
+    Ok(())
+}
+
+#[test]
+fn test_parse_month_day() -> Result<()> {
+    let test_cases = vec![
+        ("15", MonthDay::Day(15)),
+        ("last", MonthDay::LastDay),
+        ("2nd-tue", MonthDay::NthWeekday(2, Weekday::Tuesday)),
+        ("1-Monday", MonthDay::NthWeekday(1, Weekday::Monday)),
+    ];
+    for (month_day, expected) in test_cases {
+        assert_eq!(parse_month_day(month_day)?, expected);
+    }
+    for month_day in ["0", "32", "6th-mon", "2nd", "first-mon"] {
+        assert!(parse_month_day(month_day).is_err(), "{month_day}");
+    }
+    assert_eq!(parse_month_and_day("02-29")?, (2, 29));
+    for date in ["02-30", "13-01", "0314", "04-31"] {
+        assert!(parse_month_and_day(date).is_err(), "{date}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_next_monthly() {
+    let test_cases = vec![
+        (
+            MonthDay::Day(15),
+            datetime!(2023-01-20 10:00 +8),
+            datetime!(2023-02-15 9:00 +8),
+        ),
+        // months without the 31st are skipped
+        (
+            MonthDay::Day(31),
+            datetime!(2023-03-31 10:00 +8),
+            datetime!(2023-05-31 9:00 +8),
+        ),
+        (
+            MonthDay::LastDay,
+            datetime!(2023-01-31 10:00 +8),
+            datetime!(2023-02-28 9:00 +8),
+        ),
+        (
+            MonthDay::LastDay,
+            datetime!(2024-02-01 10:00 +8),
+            datetime!(2024-02-29 9:00 +8),
+        ),
+        (
+            MonthDay::NthWeekday(2, Weekday::Tuesday),
+            datetime!(2023-01-01 10:00 +8),
+            datetime!(2023-01-10 9:00 +8),
+        ),
+        // no 5th monday in 2023-02, 2023-03 and 2023-04
+        (
+            MonthDay::NthWeekday(5, Weekday::Monday),
+            datetime!(2023-01-31 10:00 +8),
+            datetime!(2023-05-29 9:00 +8),
+        ),
+        (
+            MonthDay::Day(15),
+            datetime!(2023-12-15 9:00 +8),
+            datetime!(2024-01-15 9:00 +8),
+        ),
+    ];
+    for (month_day, now, expected) in test_cases {
+        assert_eq!(
+            next_monthly(&month_day, 9, 0, now),
+            Some(expected),
+            "{month_day}"
+        );
+    }
+}
+
+#[test]
+fn test_next_yearly() {
+    let test_cases = vec![
+        (
+            (3, 14),
+            datetime!(2023-01-01 10:00 +8),
+            datetime!(2023-03-14 9:00 +8),
+        ),
+        (
+            (3, 14),
+            datetime!(2023-03-14 9:00 +8),
+            datetime!(2024-03-14 9:00 +8),
+        ),
+        (
+            (2, 29),
+            datetime!(2023-03-01 10:00 +8),
+            datetime!(2024-02-29 9:00 +8),
+        ),
+        // 2100 is not a leap year
+        (
+            (2, 29),
+            datetime!(2096-03-01 10:00 +8),
+            datetime!(2104-02-29 9:00 +8),
+        ),
+    ];
+    for ((month, day), now, expected) in test_cases {
+        assert_eq!(next_yearly(month, day, 9, 0, now), Some(expected));
+    }
+}
+
+#[test]
+fn test_parse_timezone() -> Result<()> {
+    let berlin = parse_timezone("Europe/Berlin")?;
+    let next_year = get_local_now().year() + 1;
+    // berlin is on CET in january and on CEST in july
+    let next_fire = parse_at_in(
+        &format!("{next_year}-01-10 09:00"),
+        get_local_now(),
+        Some(berlin),
+    )?;
+    assert_eq!(
+        next_fire,
+        datetime!(2000-01-10 08:00 UTC).replace_year(next_year)?
+    );
+    let next_fire = parse_at_in(
+        &format!("{next_year}-07-10 09:00"),
+        get_local_now(),
+        Some(berlin),
+    )?;
+    assert_eq!(
+        next_fire,
+        datetime!(2000-07-10 07:00 UTC).replace_year(next_year)?
+    );
+    // relative to the given now, in the time zone: 9:00 there is 7:00 UTC in summer time
+    let now = datetime!(2026-10-17 7:00 UTC);
+    assert_eq!(
+        parse_at_in("8:00", now, Some(berlin))?,
+        datetime!(2026-10-18 8:00 +2)
+    );
+    assert_eq!(
+        parse_at_in("10-17 9:30", now, Some(berlin))?,
+        datetime!(2026-10-17 9:30 +2)
+    );
+    assert!(parse_at_in("10-17 8:30", now, Some(berlin)).is_err());
+
+    for timezone in ["Europe/Nowhere", "CET+1", ""] {
+        assert!(parse_timezone(timezone).is_err(), "{timezone}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_localize() -> Result<()> {
+    let berlin = parse_timezone("Europe/Berlin")?;
+    // computed with the winter offset, but berlin is on summer time in april
+    let moment = localize(datetime!(2026-04-01 9:00 +1), Some(berlin));
+    assert_eq!(moment, datetime!(2026-04-01 9:00 +2));
+    // 02:30 doesn't exist on 2026-03-29 in berlin
+    let moment = localize(datetime!(2026-03-29 2:30 +1), Some(berlin));
+    assert_eq!(moment, datetime!(2026-03-29 3:30 +2));
+    // 02:30 happens twice on 2026-10-25 in berlin
+    let moment = localize(datetime!(2026-10-25 2:30 +1), Some(berlin));
+    assert_eq!(moment, datetime!(2026-10-25 2:30 +2));
+    Ok(())
+}
+
+// the scheduler computes the next fire in the offset of now and localizes it afterwards
+fn next_daily_in(
+    hour: u8,
+    minute: u8,
+    weekdays: &[Weekday],
+    now: time::OffsetDateTime,
+    timezone: &str,
+) -> Result<time::OffsetDateTime> {
+    let timezone = parse_timezone(timezone)?;
+    let next_fire = next_daily(hour, minute, weekdays, now).expect("no next fire");
+    Ok(localize(next_fire, Some(timezone)))
+}
+
+#[test]
+fn test_next_daily() {
+    let now = datetime!(2022-11-04 10:00 +8);
+    let test_cases = vec![
+        (9, 30, vec![], datetime!(2022-11-05 9:30 +8)),
+        (10, 30, vec![], datetime!(2022-11-04 10:30 +8)),
+        (10, 0, vec![], datetime!(2022-11-05 10:00 +8)),
+        // 2022-11-04 is a friday
+        (9, 30, vec![Weekday::Monday], datetime!(2022-11-07 9:30 +8)),
+        (
+            10,
+            30,
+            vec![Weekday::Friday, Weekday::Sunday],
+            datetime!(2022-11-04 10:30 +8),
+        ),
+    ];
+    for (hour, minute, weekdays, expected) in test_cases {
+        assert_eq!(next_daily(hour, minute, &weekdays, now), Some(expected));
+    }
+}
+
+#[test]
+fn test_next_daily_spring_forward() -> Result<()> {
+    // berlin moves from +01 to +02 at 02:00 on 2026-03-29
+    let now = datetime!(2026-03-28 10:00 +1);
+    let next_fire = next_daily_in(9, 0, &[], now, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-03-29 7:00 UTC));
+    // the skipped 02:30 fires an hour later on that day only
+    let next_fire = next_daily_in(2, 30, &[], now, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-03-29 3:30 +2));
+    let next_fire = next_daily_in(2, 30, &[], next_fire, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-03-30 2:30 +2));
+    Ok(())
+}
+
+#[test]
+fn test_next_daily_fall_back() -> Result<()> {
+    // berlin moves from +02 to +01 at 03:00 on 2026-10-25, so 02:30 happens twice
+    let now = datetime!(2026-10-24 10:00 +2);
+    let next_fire = next_daily_in(2, 30, &[], now, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-10-25 2:30 +2));
+    // fires only once: the next fire after the first 02:30 is the next day
+    let after_fire = next_fire + time::Duration::seconds(30);
+    let next_fire = next_daily_in(2, 30, &[], after_fire, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-10-26 2:30 +1));
+    let next_fire = next_daily_in(9, 0, &[], now, "Europe/Berlin")?;
+    assert_eq!(next_fire, datetime!(2026-10-25 8:00 UTC));
+    Ok(())
+}
+
+#[test]
+fn test_next_daily_half_hour_zone() -> Result<()> {
+    // 2026-01-01 18:20 UTC is already friday 2026-01-02 in kolkata
+    let now = datetime!(2026-01-01 18:20 UTC).to_offset(time::macros::offset!(+5:30));
+    let next_fire = next_daily_in(0, 10, &[Weekday::Friday], now, "Asia/Kolkata")?;
+    assert_eq!(next_fire, datetime!(2026-01-02 0:10 +5:30));
+    assert_eq!(next_fire, datetime!(2026-01-01 18:40 UTC));
+    let next_fire = next_daily_in(23, 45, &[Weekday::Thursday], now, "Asia/Kolkata")?;
+    assert_eq!(next_fire, datetime!(2026-01-08 23:45 +5:30));
+    Ok(())
+}
+
+#[test]
+fn test_parse_between() -> Result<()> {
+    assert_eq!(parse_between("09:00-18:00")?, ((9, 0), (18, 0)));
+    assert_eq!(parse_between("8:30-12:15")?, ((8, 30), (12, 15)));
+    for between in ["18:00-09:00", "09:00-09:00", "09:00", "9-18", "09:00-24:00"] {
+        assert!(parse_between(between).is_err(), "{between}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_quiet_hours() -> Result<()> {
+    let quiet_hours = parse_quiet_hours("22:00-07:30")?;
+    assert_eq!((quiet_hours.start, quiet_hours.end), ((22, 0), (7, 30)));
+    let quiet_hours = parse_quiet_hours("12:00-13:00")?;
+    assert_eq!((quiet_hours.start, quiet_hours.end), ((12, 0), (13, 0)));
+    for quiet_hours in ["22:00-22:00", "22:00", "22-7", "22:00-7:60"] {
+        assert!(parse_quiet_hours(quiet_hours).is_err(), "{quiet_hours}");
     }
+    Ok(())
+}
+
+#[test]
+fn test_effective_dnd() {
+    let now = get_local_now();
+    let later = now + time::Duration::hours(1);
+    assert_eq!(effective_dnd(None, Dnd::Off, now), Dnd::Off);
+    assert_eq!(effective_dnd(None, Dnd::On, now), Dnd::On);
+    assert_eq!(
+        effective_dnd(None, Dnd::Until(later), now),
+        Dnd::Until(later)
+    );
+    assert_eq!(effective_dnd(None, Dnd::Until(now), later), Dnd::Off);
+
+    // quiet hours from the start of this hour to two hours later, wrapping around midnight
+    let end = (now.hour() + 2) % 24;
+    let quiet_hours = QuietHours {
+        start: (now.hour(), 0),
+        end: (end, 0),
+    };
+    match effective_dnd(Some(quiet_hours), Dnd::Off, now) {
+        Dnd::Until(until) => assert_eq!((until.hour(), until.minute()), (end, 0)),
+        dnd => panic!("unexpected {dnd:?}"),
+    }
+    assert_eq!(effective_dnd(Some(quiet_hours), Dnd::On, now), Dnd::On);
+    let outside = QuietHours {
+        start: (end, 0),
+        end: (now.hour(), 0),
+    };
+    assert_eq!(effective_dnd(Some(outside), Dnd::Off, now), Dnd::Off);
+}
+
+#[test]
+fn test_parse_ics() -> Result<()> {
+    let content = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Christmas\r\n\
+        DTSTART;VALUE=DATE:20261225\r\n\
+        DTEND;VALUE=DATE:20261227\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:New Year's\r\n Day\r\n\
+        DTSTART;VALUE=DATE:2027\r\n 0101\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        DTSTART;TZID=Europe/Berlin:20270405T100000\r\n\
+        DTEND;TZID=Europe/Berlin:20270405T110000\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    let mut dates: Vec<Date> = parse_ics(content)?.into_iter().collect();
+    dates.sort();
+    assert_eq!(
+        dates,
+        vec![
+            Date::from_calendar_date(2026, Month::December, 25)?,
+            Date::from_calendar_date(2026, Month::December, 26)?,
+            Date::from_calendar_date(2027, Month::January, 1)?,
+            Date::from_calendar_date(2027, Month::April, 5)?,
+        ]
+    );
+    assert!(parse_ics("BEGIN:VEVENT\nDTSTART:20261332\nEND:VEVENT\n").is_err());
+    assert!(parse_ics("BEGIN:VEVENT\nSUMMARY:no start\nEND:VEVENT\n").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_jitter_offset() {
+    let jitter = time::Duration::minutes(10);
+    let occurrence = datetime!(2022-11-04 09:00 +1);
+    let offsets: Vec<time::Duration> = (0..20)
+        .map(|day| jitter_offset(42, occurrence + time::Duration::days(day), jitter))
+        .collect();
+    assert!(offsets
+        .iter()
+        .all(|offset| *offset >= time::Duration::ZERO && *offset < jitter));
+    // the same for the same seed and occurrence, but not a constant
+    assert_eq!(offsets[0], jitter_offset(42, occurrence, jitter));
+    assert!(offsets.iter().any(|offset| *offset != offsets[0]));
+}
+
+#[test]
+fn test_next_in_window() {
+    let window = ActiveHours {
+        start: (9, 0),
+        end: (18, 0),
+        weekdays: vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ],
+    };
+    let period = Duration::from_secs(45 * 60);
+    // 2022-11-04 is a friday
+    let test_cases = vec![
+        // the fires keep to 9:45, 10:30, 11:15... whenever the task was scheduled
+        (
+            datetime!(2022-11-03 10:00 +8),
+            datetime!(2022-11-03 10:30 +8),
+        ),
+        (
+            datetime!(2022-11-03 10:07 +8),
+            datetime!(2022-11-03 10:30 +8),
+        ),
+        (
+            datetime!(2022-11-03 10:30 +8),
+            datetime!(2022-11-03 11:15 +8),
+        ),
+        (
+            datetime!(2022-11-03 17:15 +8),
+            datetime!(2022-11-03 18:00 +8),
+        ),
+        // the phase restarts the next morning
+        (
+            datetime!(2022-11-03 18:00 +8),
+            datetime!(2022-11-04 9:45 +8),
+        ),
+        (datetime!(2022-11-03 7:00 +8), datetime!(2022-11-03 9:45 +8)),
+        // no fires at weekends
+        (
+            datetime!(2022-11-04 18:00 +8),
+            datetime!(2022-11-07 9:45 +8),
+        ),
+        (
+            datetime!(2022-11-05 10:00 +8),
+            datetime!(2022-11-07 9:45 +8),
+        ),
+    ];
+    for (after, expected) in test_cases {
+        assert_eq!(
+            next_in_window(after, period, &window),
+            Some(expected),
+            "{after}"
+        );
+    }
+    // a period longer than the window never fires
+    let period = Duration::from_secs(10 * 3600);
+    assert_eq!(
+        next_in_window(datetime!(2022-11-03 7:00 +8), period, &window),
+        None
+    );
+}
+
+#[test]
+fn test_next_fire_after() {
+    let now = datetime!(2022-11-03 17:20 +8);
+    let task = |clock_type: ClockType, options: TaskOptions| {
+        Task::new("foo".to_owned(), clock_type).with_options(TaskOptions {
+            timezone: Some("Asia/Shanghai".to_owned()),
+            ..options
+        })
+    };
+    let test_cases = vec![
+        (
+            task(
+                ClockType::Once(datetime!(2022-11-03 18:00 +8)),
+                TaskOptions::default(),
+            ),
+            Some(datetime!(2022-11-03 18:00 +8)),
+        ),
+        (
+            task(
+                ClockType::Once(datetime!(2022-11-03 17:00 +8)),
+                TaskOptions::default(),
+            ),
+            None,
+        ),
+        (
+            task(
+                ClockType::Once(datetime!(2022-11-03 18:00 +8)),
+                TaskOptions {
+                    warn: vec!["30m".to_owned()],
+                    ..Default::default()
+                },
+            ),
+            Some(datetime!(2022-11-03 17:30 +8)),
+        ),
+        (
+            task(ClockType::OncePerDay(9, 30, vec![]), TaskOptions::default()),
+            Some(datetime!(2022-11-04 9:30 +8)),
+        ),
+        // the phase is kept from the start rather than from now
+        (
+            task(
+                ClockType::Period("1h30m".to_owned()),
+                TaskOptions {
+                    start: Some(datetime!(2022-11-03 9:00 +8)),
+                    ..Default::default()
+                },
+            ),
+            Some(datetime!(2022-11-03 18:00 +8)),
+        ),
+        (
+            task(
+                ClockType::Cron("0 9 * * 1-5".to_owned()),
+                TaskOptions::default(),
+            ),
+            Some(datetime!(2022-11-04 9:00 +8)),
+        ),
+        (
+            task(
+                ClockType::Monthly(MonthDay::LastDay, 9, 0),
+                TaskOptions::default(),
+            ),
+            Some(datetime!(2022-11-30 9:00 +8)),
+        ),
+        (
+            task(ClockType::Yearly(3, 14, 9, 0), TaskOptions::default()),
+            Some(datetime!(2023-03-14 9:00 +8)),
+        ),
+        (
+            task(
+                ClockType::RRule(
+                    "FREQ=WEEKLY;BYDAY=MO".to_owned(),
+                    datetime!(2022-10-31 8:00 +8),
+                ),
+                TaskOptions::default(),
+            ),
+            Some(datetime!(2022-11-07 8:00 +8)),
+        ),
+        (
+            task(
+                ClockType::OncePerDay(9, 30, vec![]),
+                TaskOptions {
+                    until: Some(datetime!(2022-11-04 9:00 +8)),
+                    ..Default::default()
+                },
+            ),
+            None,
+        ),
+    ];
+    for (task, expected) in test_cases {
+        assert_eq!(task.next_fire_after(now), expected, "{}", task.clock_type);
+    }
+
+    // the time zone of the task applies rather than the offset of now
+    let berlin = Task::new("foo".to_owned(), ClockType::OncePerDay(9, 30, vec![])).with_options(
+        TaskOptions {
+            timezone: Some("Europe/Berlin".to_owned()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        berlin.next_fire_after(now),
+        Some(datetime!(2022-11-04 9:30 +1))
+    );
+    // and so does the jitter
+    let jittered = task(
+        ClockType::OncePerDay(9, 30, vec![]),
+        TaskOptions {
+            jitter: Some("10m".to_owned()),
+            ..Default::default()
+        },
+    );
+    let next_fire = jittered.next_fire_after(now).unwrap();
+    assert!(
+        (datetime!(2022-11-04 9:30 +8)..datetime!(2022-11-04 9:40 +8)).contains(&next_fire),
+        "{next_fire}"
+    );
+    assert_eq!(jittered.next_fire_after(now), Some(next_fire));
+}
+
+#[test]
+fn test_escalation() {
+    let escalation = Escalation::profile("10m".to_owned());
+    let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+    // the notifications after the fire: a normal one at 5m, then critical ones every minute
+    let mut elapsed = Duration::ZERO;
+    let mut notified = vec![];
+    while let Some(next) = escalation.next_after(elapsed) {
+        notified.push(next);
+        elapsed = next;
+    }
+    assert_eq!(
+        notified,
+        vec![minutes(5), minutes(6), minutes(7), minutes(8), minutes(9)]
+    );
+    assert_eq!(
+        escalation.next_after(Duration::from_secs(400)),
+        Some(minutes(7))
+    );
+
+    let urgency = |elapsed| escalation.step_at(elapsed).map(|step| step.urgency);
+    assert_eq!(urgency(Duration::ZERO), Some(Urgency::Low));
+    assert_eq!(urgency(minutes(5)), Some(Urgency::Normal));
+    assert_eq!(urgency(minutes(8)), Some(Urgency::Critical));
+    assert!(!escalation.step_at(Duration::ZERO).unwrap().sound);
 }
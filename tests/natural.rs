@@ -0,0 +1,87 @@
+use anyhow::Result;
+use task_reminder::comm::parse_timezone;
+use task_reminder::natural::parse_natural;
+use time::macros::datetime;
+
+#[test]
+fn test_parse_natural() -> Result<()> {
+    // a wednesday afternoon
+    let now = datetime!(2026-10-14 15:20 +2);
+    let berlin = Some(parse_timezone("Europe/Berlin")?);
+    let test_cases = vec![
+        ("in 2 hours", datetime!(2026-10-14 17:20 +2)),
+        ("in an hour 30 mins", datetime!(2026-10-14 16:50 +2)),
+        ("in 1h30m", datetime!(2026-10-14 16:50 +2)),
+        ("in 3 days", datetime!(2026-10-17 15:20 +2)),
+        ("in a week", datetime!(2026-10-21 15:20 +2)),
+        ("tomorrow at 6pm", datetime!(2026-10-15 18:00 +2)),
+        ("Tomorrow 6 PM", datetime!(2026-10-15 18:00 +2)),
+        ("tomorrow", datetime!(2026-10-15 9:00 +2)),
+        ("today at 18:30", datetime!(2026-10-14 18:30 +2)),
+        ("next monday 9am", datetime!(2026-10-19 9:00 +2)),
+        ("friday noon", datetime!(2026-10-16 12:00 +2)),
+        ("at 12:30am on saturday", datetime!(2026-10-17 0:30 +2)),
+        ("midnight", datetime!(2026-10-15 0:00 +2)),
+        ("8pm", datetime!(2026-10-14 20:00 +2)),
+        // the time has passed today
+        ("9am", datetime!(2026-10-15 9:00 +2)),
+        ("wednesday 9am", datetime!(2026-10-21 9:00 +2)),
+        ("wednesday 5pm", datetime!(2026-10-14 17:00 +2)),
+        ("next wednesday 5pm", datetime!(2026-10-21 17:00 +2)),
+        ("this sunday", datetime!(2026-10-18 9:00 +2)),
+    ];
+    for (expression, expected) in test_cases {
+        assert_eq!(
+            parse_natural(expression, now, berlin)?,
+            expected,
+            "{expression}"
+        );
+    }
+
+    // the day after takes the offset it has in the time zone, rather than the one of now
+    let now = datetime!(2026-10-24 15:20 +2);
+    let test_cases = vec![
+        ("tomorrow at 9am", datetime!(2026-10-25 9:00 +1)),
+        ("next monday 9am", datetime!(2026-10-26 9:00 +1)),
+    ];
+    for (expression, expected) in test_cases {
+        let moment = parse_natural(expression, now, berlin)?;
+        assert_eq!(moment, expected, "{expression}");
+        assert_eq!(moment.offset(), expected.offset(), "{expression}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_natural_err() -> Result<()> {
+    let now = datetime!(2026-10-14 15:20 +2);
+    let berlin = Some(parse_timezone("Europe/Berlin")?);
+    let test_cases = vec![
+        "",
+        "in",
+        "in 2",
+        "in 2 fortnights",
+        "in 0 minutes",
+        "in 99999999999 weeks",
+        "in 99999999999999999 weeks",
+        "in 9223372036854775807 seconds 1 second",
+        "today at 9am",
+        "tomorrow tomorrow",
+        "6pm 7pm",
+        "next",
+        "next week",
+        "13pm",
+        "0am",
+        "25:00",
+        "soon",
+        "monkey 9am",
+        "aaé",
+    ];
+    for expression in test_cases {
+        assert!(
+            parse_natural(expression, now, berlin).is_err(),
+            "{expression}"
+        );
+    }
+    Ok(())
+}
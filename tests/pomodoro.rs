@@ -0,0 +1,70 @@
+use task_reminder::pomodoro::{Phase, Pomodoro};
+use time::macros::datetime;
+use time::Duration;
+
+fn pomodoro() -> Pomodoro {
+    Pomodoro {
+        start: datetime!(2022-11-04 09:00 +1),
+        work: "25m".to_owned(),
+        short_break: "5m".to_owned(),
+        long_break: "15m".to_owned(),
+        rounds: 3,
+    }
+}
+
+#[test]
+fn test_pomodoro_phases() {
+    let pomodoro = pomodoro();
+    assert_eq!(
+        pomodoro.phases(),
+        vec![
+            (Phase::Work(1), datetime!(2022-11-04 09:25 +1)),
+            (Phase::ShortBreak(1), datetime!(2022-11-04 09:30 +1)),
+            (Phase::Work(2), datetime!(2022-11-04 09:55 +1)),
+            (Phase::ShortBreak(2), datetime!(2022-11-04 10:00 +1)),
+            (Phase::Work(3), datetime!(2022-11-04 10:25 +1)),
+            (Phase::LongBreak, datetime!(2022-11-04 10:40 +1)),
+        ]
+    );
+    assert_eq!(pomodoro.end(), datetime!(2022-11-04 10:40 +1));
+    assert_eq!(
+        pomodoro.next_change_after(datetime!(2022-11-04 09:25 +1)),
+        Some(datetime!(2022-11-04 09:30 +1))
+    );
+    assert_eq!(pomodoro.next_change_after(pomodoro.end()), None);
+}
+
+#[test]
+fn test_pomodoro_status() {
+    let pomodoro = pomodoro();
+    let test_cases = vec![
+        (datetime!(2022-11-04 08:59 +1), "not started"),
+        (
+            datetime!(2022-11-04 09:00 +1),
+            "work, round 1/3, 25m00s left",
+        ),
+        (datetime!(2022-11-04 09:27:30 +1), "short break, 2m30s left"),
+        (datetime!(2022-11-04 10:39 +1), "long break, 1m00s left"),
+        (datetime!(2022-11-04 10:40 +1), "done"),
+    ];
+    for (now, expected) in test_cases {
+        assert_eq!(pomodoro.status(now), expected, "{now}");
+    }
+    assert_eq!(
+        pomodoro.announce(pomodoro.start + Duration::minutes(30)),
+        "back to work, round 2/3 (25m)"
+    );
+    assert_eq!(pomodoro.announce(pomodoro.end()), "pomodoro done");
+}
+
+#[test]
+fn test_pomodoro_too_far_away() {
+    let too_far = Pomodoro {
+        work: "999999999d".to_owned(),
+        ..pomodoro()
+    };
+    assert!(!too_far.is_representable());
+    assert_eq!(too_far.phases(), vec![]);
+    assert_eq!(too_far.end(), too_far.start);
+    assert!(pomodoro().is_representable());
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use task_reminder::comm::parse_timezone;
+use task_reminder::rrule::parse_rrule;
+use time::macros::datetime;
+use time::OffsetDateTime;
+
+fn first_occurrences(rule: &str, dtstart: OffsetDateTime, n: usize) -> Result<Vec<OffsetDateTime>> {
+    Ok(parse_rrule(rule)?
+        .occurrences(dtstart, None)
+        .take(n)
+        .collect())
+}
+
+#[test]
+fn test_rrule_weekly() -> Result<()> {
+    // 2026-10-05 is a monday
+    let dtstart = datetime!(2026-10-05 9:30 +2);
+    let occurrences = first_occurrences("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR", dtstart, 4)?;
+    assert_eq!(
+        occurrences,
+        vec![
+            datetime!(2026-10-05 9:30 +2),
+            datetime!(2026-10-09 9:30 +2),
+            datetime!(2026-10-19 9:30 +2),
+            datetime!(2026-10-23 9:30 +2),
+        ]
+    );
+    // without BYDAY the weekday of dtstart is used
+    let occurrences = first_occurrences("RRULE:FREQ=WEEKLY", dtstart, 2)?;
+    assert_eq!(
+        occurrences,
+        vec![datetime!(2026-10-05 9:30 +2), datetime!(2026-10-12 9:30 +2)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rrule_monthly() -> Result<()> {
+    let dtstart = datetime!(2026-01-01 8:00 +0);
+    // the last weekday of the month
+    let occurrences =
+        first_occurrences("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", dtstart, 3)?;
+    assert_eq!(
+        occurrences,
+        vec![
+            datetime!(2026-01-30 8:00 +0),
+            datetime!(2026-02-27 8:00 +0),
+            datetime!(2026-03-31 8:00 +0),
+        ]
+    );
+    let occurrences = first_occurrences("FREQ=MONTHLY;BYDAY=2TU", dtstart, 2)?;
+    assert_eq!(
+        occurrences,
+        vec![datetime!(2026-01-13 8:00 +0), datetime!(2026-02-10 8:00 +0)]
+    );
+    let occurrences = first_occurrences("FREQ=MONTHLY;BYMONTHDAY=-1", dtstart, 2)?;
+    assert_eq!(
+        occurrences,
+        vec![datetime!(2026-01-31 8:00 +0), datetime!(2026-02-28 8:00 +0)]
+    );
+    // friday the 13th
+    let occurrences = first_occurrences("FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13", dtstart, 2)?;
+    assert_eq!(
+        occurrences,
+        vec![datetime!(2026-02-13 8:00 +0), datetime!(2026-03-13 8:00 +0)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rrule_yearly() -> Result<()> {
+    let dtstart = datetime!(2024-02-29 10:00 +0);
+    let occurrences = first_occurrences("FREQ=YEARLY", dtstart, 2)?;
+    assert_eq!(
+        occurrences,
+        vec![
+            datetime!(2024-02-29 10:00 +0),
+            datetime!(2028-02-29 10:00 +0)
+        ]
+    );
+    // thanksgiving
+    let occurrences = first_occurrences(
+        "FREQ=YEARLY;BYMONTH=11;BYDAY=4TH",
+        datetime!(2026-01-01 9:00 +0),
+        2,
+    )?;
+    assert_eq!(
+        occurrences,
+        vec![datetime!(2026-11-26 9:00 +0), datetime!(2027-11-25 9:00 +0)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rrule_count_until() -> Result<()> {
+    let dtstart = datetime!(2026-12-17 9:00 +1);
+    let occurrences = first_occurrences("FREQ=DAILY;COUNT=3", dtstart, 10)?;
+    assert_eq!(occurrences.len(), 3);
+    let occurrences = first_occurrences("FREQ=DAILY;UNTIL=20261220", dtstart, 10)?;
+    assert_eq!(occurrences.last(), Some(&datetime!(2026-12-20 9:00 +1)));
+    assert_eq!(occurrences.len(), 4);
+    let occurrences = first_occurrences("FREQ=DAILY;UNTIL=20261219T080000Z", dtstart, 10)?;
+    assert_eq!(occurrences.len(), 3);
+
+    let rule = parse_rrule("FREQ=DAILY;COUNT=3")?;
+    assert_eq!(
+        rule.next_after(dtstart, datetime!(2026-12-18 12:00 +1), None),
+        Some(datetime!(2026-12-19 9:00 +1))
+    );
+    assert_eq!(
+        rule.next_after(dtstart, datetime!(2026-12-19 9:00 +1), None),
+        None
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rrule_across_dst() -> Result<()> {
+    // 9:30 in Berlin is 7:30 UTC in summer time and 8:30 UTC after it ends on 2022-10-30
+    let berlin = Some(parse_timezone("Europe/Berlin")?);
+    let dtstart = datetime!(2022-10-29 9:30 +2);
+    let rule = parse_rrule("FREQ=DAILY")?;
+    assert_eq!(
+        rule.next_after(dtstart, datetime!(2022-10-30 8:00 UTC), berlin),
+        Some(datetime!(2022-10-30 8:30 UTC))
+    );
+    let rule = parse_rrule("FREQ=DAILY;UNTIL=20221030T080000Z")?;
+    assert_eq!(
+        rule.next_after(dtstart, datetime!(2022-10-29 12:00 UTC), berlin),
+        None
+    );
+    // an inclusive UNTIL date ends in the time zone, not at the offset of dtstart
+    let rule = parse_rrule("FREQ=DAILY;UNTIL=20221030")?;
+    let dtstart = datetime!(2022-10-29 23:30 +2);
+    assert_eq!(
+        rule.next_after(dtstart, datetime!(2022-10-29 22:00 UTC), berlin),
+        Some(datetime!(2022-10-30 22:30 UTC))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rrule_summary() -> Result<()> {
+    let dtstart = datetime!(2026-10-05 9:30 +2);
+    let test_cases = vec![
+        (
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,FR;COUNT=10",
+            "every 2 weeks on mon,fri at 9:30, 10 times",
+        ),
+        (
+            "FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20261220",
+            "every month on last fri at 9:30, until 2026-12-20",
+        ),
+        (
+            "FREQ=YEARLY;BYMONTH=3;BYMONTHDAY=14",
+            "every year in mar on the 14th at 9:30",
+        ),
+    ];
+    for (rule, expected) in test_cases {
+        assert_eq!(parse_rrule(rule)?.summary(dtstart), expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_rrule_err() {
+    let test_cases = vec![
+        "",
+        "BYDAY=MO",
+        "FREQ=HOURLY",
+        "FREQ=DAILY;INTERVAL=0",
+        "FREQ=DAILY;COUNT=2;UNTIL=20261220",
+        "FREQ=WEEKLY;BYDAY=2MO",
+        "FREQ=WEEKLY;BYMONTHDAY=1",
+        "FREQ=MONTHLY;BYMONTHDAY=32",
+        "FREQ=MONTHLY;BYDAY=XX",
+        "FREQ=YEARLY;BYMONTH=13",
+        "FREQ=DAILY;UNTIL=2026-12-20",
+        "FREQ=DAILY;FOO=BAR",
+    ];
+    for rule in test_cases {
+        assert!(parse_rrule(rule).is_err(), "{rule}");
+    }
+}
@@ -0,0 +1,28 @@
+use task_reminder::timer::Timer;
+use time::macros::datetime;
+use time::Duration;
+
+#[test]
+fn test_timer_pause_and_resume() {
+    let timer = Timer::new("25m".to_owned(), datetime!(2022-11-04 09:25 +1));
+    let now = datetime!(2022-11-04 09:10 +1);
+    assert_eq!(timer.remaining(now), Duration::minutes(15));
+    assert_eq!(timer.status(now), "15m00s left");
+    assert_eq!(
+        timer.remaining(datetime!(2022-11-04 09:30 +1)),
+        Duration::ZERO
+    );
+
+    // the time left is kept while paused
+    let paused = timer.paused(now);
+    let later = datetime!(2022-11-04 11:00 +1);
+    assert_eq!(paused.remaining(later), Duration::minutes(15));
+    assert_eq!(paused.status(later), "paused, 15m00s left");
+    let resumed = paused.resumed(later);
+    assert_eq!(resumed.end, datetime!(2022-11-04 11:15 +1));
+    assert_eq!(resumed.left, None);
+    assert_eq!(resumed.to_string(), "timer 25m");
+
+    let timer = Timer::new("2h".to_owned(), datetime!(2022-11-04 11:05:30 +1));
+    assert_eq!(timer.status(now), "1h55m30s left");
+}